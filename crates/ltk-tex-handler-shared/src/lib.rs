@@ -1,5 +1,7 @@
 //! Constants shared between the `.tex` shell handler DLL (`ltk-tex-thumb-handler`)
-//! and the `ltk-tex-utils` CLI that installs and inspects it.
+//! and the `ltk-tex-utils` CLI that installs and inspects it, plus the texture
+//! metadata lines both show (the preview pane overlay and `ltk-tex-utils view`)
+//! and the thumbnail scaling `ltk-tex-utils montage` reuses for its tiles.
 //!
 //! These are the registry identifiers and the override toggle that both crates
 //! must agree on: the DLL writes them during registration, and the CLI reads them
@@ -70,3 +72,86 @@ pub const PACKAGE_IDENTITY_NAME: &str = "LeagueToolkit.ltk-tex-utils";
 /// registered unsigned through Developer Mode's loose-manifest path, which
 /// rejects the `OID.2.25...` unsigned-namespace marker used by `-AllowUnsigned`.
 pub const PACKAGE_PUBLISHER: &str = "CN=LeagueToolkit";
+
+/// Human-facing metadata about a decoded TEX, shown by the preview pane overlay,
+/// the property sheet and `ltk-tex-utils view`.
+pub struct TexMeta {
+    pub format: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub mip_count: u32,
+    pub has_alpha: bool,
+}
+
+impl TexMeta {
+    /// The metadata lines, top to bottom.
+    pub fn lines(&self) -> Vec<String> {
+        vec![
+            format!("{} x {}", self.width, self.height),
+            format!("Format: {}", self.format),
+            format!("Mips: {}", self.mip_count),
+            format!("Alpha: {}", if self.has_alpha { "yes" } else { "no" }),
+        ]
+    }
+}
+
+/// Scale an RGBA image (nearest neighbor) so its longer side is `cx` pixels,
+/// keeping its aspect ratio (the shorter side is at least 1 pixel). Returns the
+/// pixels and their dimensions.
+pub fn scale_image(src: &[u8], src_w: u32, src_h: u32, cx: u32) -> (Vec<u8>, u32, u32) {
+    let (dst_w, dst_h) = if src_w >= src_h {
+        (cx, ((src_h * cx + src_w / 2) / src_w).max(1))
+    } else {
+        (((src_w * cx + src_h / 2) / src_h).max(1), cx)
+    };
+
+    let mut out = vec![0u8; (dst_w * dst_h * 4) as usize];
+    for y in 0..dst_h {
+        let sy = (y * src_h / dst_h).clamp(0, src_h - 1);
+        for x in 0..dst_w {
+            let sx = (x * src_w / dst_w).clamp(0, src_w - 1);
+            let si = ((sy * src_w + sx) * 4) as usize;
+            let di = ((y * dst_w + x) * 4) as usize;
+            out[di..di + 4].copy_from_slice(&src[si..si + 4]);
+        }
+    }
+    (out, dst_w, dst_h)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_expected_metadata_lines() {
+        let meta = TexMeta {
+            format: "BC7",
+            width: 1024,
+            height: 512,
+            mip_count: 11,
+            has_alpha: true,
+        };
+        assert_eq!(
+            meta.lines(),
+            vec![
+                "1024 x 512".to_string(),
+                "Format: BC7".to_string(),
+                "Mips: 11".to_string(),
+                "Alpha: yes".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn scales_the_longer_side_to_fit() {
+        let src = [[255u8, 0, 0, 255], [0, 0, 255, 255]].concat();
+        let (out, w, h) = scale_image(&src, 2, 1, 4);
+        assert_eq!((w, h), (4, 2));
+        assert_eq!(&out[..8], &[255, 0, 0, 255, 255, 0, 0, 255]);
+        assert_eq!(&out[8..16], &[0, 0, 255, 255, 0, 0, 255, 255]);
+
+        // A 64x1 strip would round to 0 rows.
+        let (_, w, h) = scale_image(&[0; 4 * 64], 64, 1, 16);
+        assert_eq!((w, h), (16, 1));
+    }
+}
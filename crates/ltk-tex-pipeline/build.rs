@@ -0,0 +1,17 @@
+fn main() {
+    // The `intel-tex` encoders are C++/ISPC; link the C++ standard library so
+    // everything depending on this crate (tests included) links.
+    if std::env::var_os("CARGO_FEATURE_INTEL_TEX").is_none() {
+        return;
+    }
+    match std::env::var("CARGO_CFG_TARGET_OS")
+        .unwrap_or_default()
+        .as_str()
+    {
+        // On Linux (GNU/Musl), libstdc++ provides __gxx_personality_v0 and others
+        "linux" => println!("cargo:rustc-link-lib=dylib=stdc++"),
+        // On macOS, the C++ runtime is libc++
+        "macos" => println!("cargo:rustc-link-lib=dylib=c++"),
+        _ => {}
+    }
+}
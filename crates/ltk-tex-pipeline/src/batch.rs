@@ -0,0 +1,464 @@
+//! Batches: one conversion run over many files, with output collisions caught
+//! up front, overwrite policies, failure limits, progress callbacks and an
+//! optional JSON/JUnit report.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::Result;
+use crate::error::bail;
+use crate::io::{STDIO, is_stdio};
+use crate::report::{BatchReport, JobReport, JobStatus, ReportFormat};
+
+/// Error of a single job; anything that converts into it (an [`Error`],
+/// `eyre::Report`, `std::io::Error`, a `String`, ...) can be returned.
+///
+/// [`Error`]: crate::Error
+pub type JobError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Why a batch did not fully succeed. Each case has its own process exit code
+/// (see [`BatchError::exit_code`]) so wrapper scripts can tell them apart.
+#[derive(Debug)]
+pub enum BatchError {
+    /// Some files failed; every file was attempted.
+    SomeFailed { failed: usize, total: usize },
+    /// Every file failed.
+    AllFailed { total: usize },
+    /// Stopped early by [`BatchOptions::max_failures`].
+    Aborted {
+        failed: usize,
+        attempted: usize,
+        total: usize,
+    },
+}
+
+impl BatchError {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            BatchError::SomeFailed { .. } => 3,
+            BatchError::AllFailed { .. } => 4,
+            BatchError::Aborted { .. } => 5,
+        }
+    }
+}
+
+impl std::fmt::Display for BatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BatchError::SomeFailed { failed, total } => {
+                write!(f, "{failed} of {total} file(s) failed")
+            }
+            BatchError::AllFailed { total } => {
+                write!(f, "all {total} file(s) failed")
+            }
+            BatchError::Aborted {
+                failed,
+                attempted,
+                total,
+            } => write!(
+                f,
+                "aborted after {failed} failure(s); {attempted} of {total} file(s) attempted"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+/// How existing output files are treated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum OverwritePolicy {
+    /// Replace existing files
+    #[default]
+    Always,
+    /// Skip inputs whose output already exists
+    Never,
+    /// Replace existing files only when the input was modified after them
+    Newer,
+    /// Write next to existing files as `name (1).ext`, `name (2).ext`, ...
+    Rename,
+}
+
+/// A single conversion of a batch: `input` is converted and written to `output`.
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub input: PathBuf,
+    /// Empty for jobs that write nothing (e.g. `verify`).
+    pub output: String,
+}
+
+/// Pair each file with `output`, or by default its sibling with `extension`.
+pub fn sibling_jobs(files: &[PathBuf], output: Option<&str>, extension: &str) -> Vec<Job> {
+    files
+        .iter()
+        .map(|file| Job {
+            input: file.clone(),
+            output: output
+                .map(str::to_string)
+                .unwrap_or_else(|| sibling_with_extension(file, extension)),
+        })
+        .collect()
+}
+
+/// Default output path for `input`: the same path with `extension`, or stdout
+/// when reading from stdin.
+pub fn sibling_with_extension(input: &Path, extension: &str) -> String {
+    if is_stdio(input) {
+        return STDIO.to_string();
+    }
+    let mut out = input.to_path_buf();
+    out.set_extension(extension);
+    out.to_string_lossy().into_owned()
+}
+
+/// Apply `policy` to outputs that already exist: drop the jobs that must not
+/// overwrite theirs (logging why) and redirect renamed ones. Decided up front,
+/// from the state of the disk before the batch runs.
+pub fn apply_overwrite_policy(jobs: Vec<Job>, policy: OverwritePolicy) -> Vec<Job> {
+    jobs.into_iter()
+        .filter_map(|mut job| {
+            let output = Path::new(&job.output);
+            if is_stdio(output) || !output.exists() {
+                return Some(job);
+            }
+            match policy {
+                OverwritePolicy::Always => Some(job),
+                OverwritePolicy::Never => {
+                    tracing::info!(
+                        "skipping {}: {} already exists",
+                        job.input.display(),
+                        job.output
+                    );
+                    None
+                }
+                OverwritePolicy::Newer => {
+                    if is_newer(&job.input, output) {
+                        Some(job)
+                    } else {
+                        tracing::info!(
+                            "skipping {}: {} is up to date",
+                            job.input.display(),
+                            job.output
+                        );
+                        None
+                    }
+                }
+                OverwritePolicy::Rename => {
+                    job.output = free_name(output).to_string_lossy().into_owned();
+                    Some(job)
+                }
+            }
+        })
+        .collect()
+}
+
+/// Whether `input` was modified after `output` (true when either time is unknown).
+fn is_newer(input: &Path, output: &Path) -> bool {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    match (modified(input), modified(output)) {
+        (Some(input), Some(output)) => input > output,
+        _ => true,
+    }
+}
+
+/// First of `name (1).ext`, `name (2).ext`, ... that doesn't exist yet.
+fn free_name(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| path.with_file_name(format!("{stem} ({n}){extension}")))
+        .find(|candidate| !candidate.exists())
+        .expect("unbounded range")
+}
+
+/// Outputs that more than one job writes to, keyed by [`output_key`], with
+/// the number of jobs writing each.
+pub fn collisions(jobs: &[Job]) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for job in jobs
+        .iter()
+        .filter(|job| !job.output.is_empty() && !is_stdio(&job.output))
+    {
+        *counts.entry(output_key(&job.output)).or_insert(0usize) += 1;
+    }
+    counts.retain(|_, count| *count > 1);
+    counts
+}
+
+/// Key identifying an output file: paths differing only in separators or `.`
+/// components (and case, on Windows) name the same file.
+pub fn output_key(output: &str) -> String {
+    let key = Path::new(output)
+        .components()
+        .filter(|c| *c != std::path::Component::CurDir)
+        .collect::<PathBuf>()
+        .to_string_lossy()
+        .into_owned();
+    if cfg!(windows) {
+        key.to_lowercase()
+    } else {
+        key
+    }
+}
+
+/// Fail if several jobs would write the same output, before anything is written.
+fn check_collisions(jobs: &[Job]) -> Result<()> {
+    let collisions = collisions(jobs);
+    if collisions.is_empty() {
+        return Ok(());
+    }
+    for job in jobs {
+        if collisions.contains_key(&output_key(&job.output)) {
+            tracing::error!(
+                "colliding output {} (from {})",
+                job.output,
+                job.input.display()
+            );
+        }
+    }
+    bail!(
+        "{} output(s) would be written by more than one input; \
+         rename the inputs or convert them separately",
+        collisions.len()
+    );
+}
+
+/// How a batch runs and reports.
+#[derive(Debug, Clone, Default)]
+pub struct BatchOptions {
+    /// Write a report of every job (status, errors, timing, sizes) here once done.
+    pub report: Option<PathBuf>,
+    /// Report format; defaults to [`ReportFormat::from_path`] of `report`.
+    pub report_format: Option<ReportFormat>,
+    /// Stop once more than this many jobs failed; `Some(0)` stops at the first.
+    pub max_failures: Option<usize>,
+}
+
+/// Follows a batch as it runs, e.g. to draw a progress bar. Every method does
+/// nothing by default; `()` observes nothing.
+pub trait BatchObserver {
+    /// The batch is about to run `total` jobs.
+    fn started(&mut self, _total: usize) {}
+    /// `job` is about to run.
+    fn job_started(&mut self, _job: &Job) {}
+    /// `job` ran; `report` tells how it went.
+    fn job_finished(&mut self, _job: &Job, _report: &JobReport) {}
+    /// Every job ran (or was skipped), before the report is written.
+    fn finished(&mut self) {}
+}
+
+impl BatchObserver for () {}
+
+/// A batch conversion run by one command.
+pub struct Batch<'a> {
+    /// Command name, recorded in reports.
+    pub command: &'a str,
+    /// Format the outputs are written in, recorded in reports.
+    pub format: String,
+    pub options: BatchOptions,
+}
+
+impl Batch<'_> {
+    /// Run each job, logging failures and continuing unless
+    /// [`BatchOptions::max_failures`] says otherwise; errors out with a
+    /// [`BatchError`] if any failed. Writes the report, if any, once done.
+    ///
+    /// Nothing runs if two jobs share an output.
+    pub fn run(
+        &self,
+        jobs: &[Job],
+        observer: &mut impl BatchObserver,
+        mut per_job: impl FnMut(&Job) -> Result<(), JobError>,
+    ) -> Result<()> {
+        check_collisions(jobs)?;
+        let started = Instant::now();
+
+        let mut reports = Vec::with_capacity(jobs.len());
+        let mut failed = 0usize;
+        let mut aborted = false;
+        observer.started(jobs.len());
+        for job in jobs {
+            if aborted {
+                reports.push(self.skipped_report(job));
+                continue;
+            }
+            observer.job_started(job);
+            let job_started = Instant::now();
+            let report = self.job_report(job, per_job(job), job_started.elapsed());
+            observer.job_finished(job, &report);
+            if report.status == JobStatus::Failed {
+                failed += 1;
+                tracing::error!(
+                    "{} failed: {}",
+                    job.input.display(),
+                    report.errors.join(": ")
+                );
+                if self.options.max_failures.is_some_and(|max| failed > max) {
+                    aborted = true;
+                }
+            }
+            reports.push(report);
+        }
+        observer.finished();
+
+        let attempted = reports
+            .iter()
+            .filter(|report| report.status != JobStatus::Skipped)
+            .count();
+        if let Some(path) = &self.options.report {
+            let report = BatchReport {
+                command: self.command.to_string(),
+                total: jobs.len(),
+                succeeded: attempted - failed,
+                failed,
+                skipped: jobs.len() - attempted,
+                elapsed_ms: millis(started.elapsed()),
+                jobs: reports,
+            };
+            let format = self
+                .options
+                .report_format
+                .unwrap_or_else(|| ReportFormat::from_path(path));
+            report.write(path, format)?;
+            tracing::info!("wrote report to {}", path.display());
+        }
+
+        let total = jobs.len();
+        match failed {
+            0 => Ok(()),
+            _ if aborted && attempted < total => Err(BatchError::Aborted {
+                failed,
+                attempted,
+                total,
+            }
+            .into()),
+            _ if failed == total => Err(BatchError::AllFailed { total }.into()),
+            _ => Err(BatchError::SomeFailed { failed, total }.into()),
+        }
+    }
+
+    fn skipped_report(&self, job: &Job) -> JobReport {
+        JobReport {
+            input: job.input.to_string_lossy().into_owned(),
+            output: job.output.clone(),
+            status: JobStatus::Skipped,
+            errors: Vec::new(),
+            elapsed_ms: 0.0,
+            input_size: None,
+            output_size: None,
+            format: self.format.clone(),
+        }
+    }
+
+    fn job_report(&self, job: &Job, result: Result<(), JobError>, elapsed: Duration) -> JobReport {
+        let size = |path: &Path| std::fs::metadata(path).ok().map(|m| m.len());
+        let (status, errors) = match result {
+            Ok(()) => (JobStatus::Ok, Vec::new()),
+            Err(err) => {
+                let mut errors = vec![err.to_string()];
+                let mut source = err.source();
+                while let Some(err) = source {
+                    errors.push(err.to_string());
+                    source = err.source();
+                }
+                (JobStatus::Failed, errors)
+            }
+        };
+        JobReport {
+            input: job.input.to_string_lossy().into_owned(),
+            output: job.output.clone(),
+            status,
+            errors,
+            elapsed_ms: millis(elapsed),
+            input_size: (!is_stdio(&job.input)).then(|| size(&job.input)).flatten(),
+            output_size: (status == JobStatus::Ok && !is_stdio(&job.output))
+                .then(|| size(Path::new(&job.output)))
+                .flatten(),
+            format: self.format.clone(),
+        }
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collisions_ignore_curdir_components() {
+        let files = [
+            PathBuf::from("a.png"),
+            PathBuf::from("./a.dds"),
+            PathBuf::from("b.png"),
+        ];
+        let collisions = collisions(&sibling_jobs(&files, None, "tex"));
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[&output_key("a.tex")], 2);
+    }
+
+    #[test]
+    fn overwrite_policies_handle_existing_outputs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let input = dir.join("a.png");
+        let output = dir.join("a.tex");
+        std::fs::write(&input, b"png").unwrap();
+        std::fs::write(&output, b"tex").unwrap();
+        std::fs::write(dir.join("a (1).tex"), b"tex").unwrap();
+        let jobs = || sibling_jobs(std::slice::from_ref(&input), None, "tex");
+
+        assert_eq!(
+            apply_overwrite_policy(jobs(), OverwritePolicy::Always).len(),
+            1
+        );
+        assert!(apply_overwrite_policy(jobs(), OverwritePolicy::Never).is_empty());
+        let renamed = apply_overwrite_policy(jobs(), OverwritePolicy::Rename);
+        assert_eq!(PathBuf::from(&renamed[0].output), dir.join("a (2).tex"));
+    }
+
+    #[test]
+    fn stops_after_max_failures_and_tells_the_observer() {
+        #[derive(Default)]
+        struct Counts(usize, usize);
+        impl BatchObserver for Counts {
+            fn job_started(&mut self, _job: &Job) {
+                self.0 += 1;
+            }
+            fn job_finished(&mut self, _job: &Job, report: &JobReport) {
+                self.1 += report.errors.len();
+            }
+        }
+
+        let files = ["a", "b", "c"].map(PathBuf::from);
+        let jobs = sibling_jobs(&files, None, "tex");
+        let batch = Batch {
+            command: "test",
+            format: "tex".to_string(),
+            options: BatchOptions {
+                max_failures: Some(0),
+                ..BatchOptions::default()
+            },
+        };
+        let mut counts = Counts::default();
+        let result = batch.run(&jobs, &mut counts, |job| {
+            Err(format!("cannot convert {}", job.input.display()).into())
+        });
+        assert!(matches!(
+            result,
+            Err(crate::Error::Batch(BatchError::Aborted {
+                failed: 1,
+                attempted: 1,
+                total: 3
+            }))
+        ));
+        assert_eq!((counts.0, counts.1), (1, 1));
+    }
+}
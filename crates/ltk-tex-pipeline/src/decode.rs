@@ -0,0 +1,237 @@
+//! `.tex` to images: in memory (what the Explorer handlers show) or to PNG,
+//! DDS and other image files, with the faces of cubemaps and the slices of
+//! volume textures exported as the [`LayerMode`] says.
+
+#[cfg(feature = "convert")]
+use std::io::{Cursor, Write};
+#[cfg(feature = "convert")]
+use std::path::{Path, PathBuf};
+
+use image::RgbaImage;
+#[cfg(feature = "convert")]
+use image_dds::{ImageFormat, Mipmaps, Quality, SurfaceRgba8};
+use ltk_tex_handler_shared::TexMeta;
+use ltk_texture::Tex;
+#[cfg(feature = "convert")]
+use ltk_texture::tex::ResourceType;
+
+use crate::Result;
+#[cfg(feature = "convert")]
+use crate::error::bail;
+#[cfg(feature = "convert")]
+use crate::format::DecodeOutputFormat;
+use crate::format::format_name;
+#[cfg(feature = "convert")]
+use crate::io::{create_output, is_stdio, open_input};
+use crate::layers;
+#[cfg(feature = "convert")]
+use crate::layers::LayerMode;
+#[cfg(feature = "convert")]
+use crate::texdata::{self, Header};
+
+/// How textures are decoded to files.
+#[cfg(feature = "convert")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DecodeOptions {
+    /// Image format written to stdout (`-`); files use their extension.
+    pub format: DecodeOutputFormat,
+    /// Mipmap to decode (0 = largest).
+    pub mipmap: u32,
+    pub layers: LayerMode,
+}
+
+/// Parse a `.tex` file.
+pub fn read_tex(bytes: &[u8]) -> Result<Tex> {
+    Ok(Tex::from_reader(&mut &bytes[..])?)
+}
+
+/// The largest mip of a `.tex` file as RGBA8; the first face/slice of
+/// cubemaps and volume textures.
+pub fn decode_rgba(bytes: &[u8]) -> Result<RgbaImage> {
+    layers::decode_layer(&read_tex(bytes)?, 0, 0)
+}
+
+/// [`decode_rgba`] plus the header metadata the preview pane and property
+/// columns show. Alpha is judged from the decoded pixels, not the format.
+pub fn decode_with_meta(bytes: &[u8]) -> Result<(RgbaImage, TexMeta)> {
+    let tex = read_tex(bytes)?;
+    let image = layers::decode_layer(&tex, 0, 0)?;
+    let meta = TexMeta {
+        format: format_name(tex.format),
+        width: image.width(),
+        height: image.height(),
+        mip_count: tex.mip_count,
+        has_alpha: image.pixels().any(|pixel| pixel[3] != u8::MAX),
+    };
+    Ok((image, meta))
+}
+
+/// Decode the texture at `input` (`-` for stdin) to `output` (`-` for stdout).
+#[cfg(feature = "convert")]
+pub fn decode_file(input: &str, output: &str, options: &DecodeOptions) -> Result<()> {
+    let tex = Tex::from_reader(&mut open_input(input)?)?;
+    decode_tex(&tex, output, options)
+}
+
+/// Decode an already loaded texture to `output` (`-` for stdout).
+#[cfg(feature = "convert")]
+pub fn decode_tex(tex: &Tex, output: &str, options: &DecodeOptions) -> Result<()> {
+    let as_dds = if is_stdio(output) {
+        options.format == DecodeOutputFormat::Dds
+    } else {
+        is_dds(Path::new(output))
+    };
+
+    texdata::check_level(&Header::of(tex), options.mipmap)?;
+    if options.layers == LayerMode::First || !layers::is_layered(tex) {
+        let image = tex.decode_mipmap(options.mipmap)?.into_rgba_image()?;
+        return write_image(&image, output, as_dds);
+    }
+
+    let faces = layers::decode_layers(tex, options.mipmap)?;
+    match options.layers {
+        LayerMode::First => unreachable!("handled above"),
+        LayerMode::Split => {
+            if is_stdio(output) {
+                bail!("--layers split writes several files and cannot target stdout");
+            }
+            for (layer, image) in faces.iter().enumerate() {
+                let suffix = layers::layer_suffix(tex.resource_type, layer as u32);
+                let path = with_suffix(Path::new(output), &suffix);
+                write_image(image, &path.to_string_lossy(), as_dds)?;
+            }
+            Ok(())
+        }
+        LayerMode::Cross => {
+            if tex.resource_type != ResourceType::Cubemap {
+                bail!(
+                    "--layers cross only applies to cubemaps; this is a {:?}",
+                    tex.resource_type
+                );
+            }
+            write_image(&layers::compose_cross(&faces)?, output, as_dds)
+        }
+        LayerMode::Array => {
+            if !as_dds {
+                bail!("--layers array needs a .dds output (use -f dds or an -o ending in .dds)");
+            }
+            // The faces/slices are stacked vertically, which is the layout
+            // image_dds expects for both array layers and depth slices.
+            let stacked = layers::stack(&faces);
+            let surface = if tex.resource_type == ResourceType::Cubemap {
+                SurfaceRgba8::from_image_layers(&stacked, faces.len() as u32)
+            } else {
+                SurfaceRgba8::from_image_depth(&stacked, faces.len() as u32)
+            };
+            let dds =
+                surface.encode_dds(ImageFormat::Rgba8Unorm, Quality::Fast, Mipmaps::Disabled)?;
+            let mut writer = create_output(output)?;
+            dds.write(&mut writer)?;
+            writer.commit()?;
+            Ok(())
+        }
+    }
+}
+
+/// Write a decoded image, as an uncompressed DDS when `as_dds` and through the
+/// `image` crate otherwise (format inferred from the extension; PNG for stdout).
+#[cfg(feature = "convert")]
+fn write_image(image: &RgbaImage, output: &str, as_dds: bool) -> Result<()> {
+    let mut writer = create_output(output)?;
+    if as_dds {
+        // The `image` crate cannot write DDS; emit an uncompressed RGBA8 DDS
+        // holding the single decoded mip.
+        let dds = image_dds::dds_from_image(
+            image,
+            ImageFormat::Rgba8Unorm,
+            Quality::Fast,
+            Mipmaps::Disabled,
+        )?;
+        dds.write(&mut writer)?;
+    } else {
+        let format = if is_stdio(output) {
+            image::ImageFormat::Png
+        } else {
+            image::ImageFormat::from_path(output)?
+        };
+        // Most `image` encoders need a seekable writer, so encode in memory first.
+        let mut encoded = Cursor::new(Vec::new());
+        image.write_to(&mut encoded, format)?;
+        writer.write_all(encoded.get_ref())?;
+    }
+    writer.commit()?;
+    Ok(())
+}
+
+#[cfg(feature = "convert")]
+fn is_dds(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("dds"))
+}
+
+/// `dir/name.png` -> `dir/name_<suffix>.png`
+#[cfg(feature = "convert")]
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{stem}_{suffix}");
+    if let Some(ext) = path.extension() {
+        name.push('.');
+        name.push_str(&ext.to_string_lossy());
+    }
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal uncompressed BGRA8 .tex (format id 20), no mipmaps.
+    fn bgra8_tex(width: u16, height: u16, pixels_bgra: &[u8]) -> Vec<u8> {
+        let mut f = Vec::new();
+        f.extend_from_slice(b"TEX\0");
+        f.extend_from_slice(&width.to_le_bytes());
+        f.extend_from_slice(&height.to_le_bytes());
+        f.push(1); // depth
+        f.push(20); // format: Bgra8
+        f.push(0); // resource type: texture
+        f.push(0); // flags: no mipmaps
+        f.extend_from_slice(pixels_bgra);
+        f
+    }
+
+    #[test]
+    fn decode_with_meta_reports_dimensions_format_and_alpha() {
+        // 2x2 BGRA8: pixel 0 is red at 50% alpha, the rest opaque white.
+        let px = [
+            0x00, 0x00, 0xFF, 0x80, // BGRA red, a=128
+            0xFF, 0xFF, 0xFF, 0xFF, //
+            0xFF, 0xFF, 0xFF, 0xFF, //
+            0xFF, 0xFF, 0xFF, 0xFF, //
+        ];
+        let file = bgra8_tex(2, 2, &px);
+
+        let (rgba, meta) = decode_with_meta(&file).expect("decode");
+        assert_eq!(rgba.dimensions(), (2, 2));
+        assert_eq!(meta.format, "BGRA8");
+        assert_eq!(meta.mip_count, 1);
+        assert!(meta.has_alpha, "a=128 pixel should be detected");
+        // First decoded pixel is red with alpha 128.
+        assert_eq!(&rgba.as_raw()[0..4], &[0xFF, 0x00, 0x00, 0x80]);
+    }
+
+    #[cfg(feature = "convert")]
+    #[test]
+    fn decode_tex_rejects_out_of_range_mips_of_2d_textures() {
+        let tex = read_tex(&bgra8_tex(1, 1, &[0xFF; 4])).unwrap();
+        let options = DecodeOptions {
+            mipmap: 1,
+            ..Default::default()
+        };
+        let err = decode_tex(&tex, "-", &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "mip 1 out of range (the texture has 1 mip level(s))"
+        );
+    }
+}
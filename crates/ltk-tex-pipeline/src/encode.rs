@@ -0,0 +1,132 @@
+//! Images to `.tex`: loading PNG/TGA/DDS/... inputs and encoding them, as
+//! single textures or as cubemaps.
+
+use image::{ImageFormat, RgbaImage};
+use ltk_texture::tex::MipmapFilter;
+use ltk_texture::{Dds, DecompressError, Tex, ToImageError};
+
+use crate::Result;
+use crate::error::bail;
+use crate::format::ValidFormat;
+use crate::io::{create_output, read_input};
+use crate::layers;
+
+/// How images are encoded. The default matches `ltk-tex-utils encode`: BC3
+/// with a Catmull-Rom mip chain.
+#[derive(Debug, Clone, Copy)]
+pub struct EncodeOptions {
+    /// Overrides the input image type inferred from the extension/content.
+    pub input_format: Option<ImageFormat>,
+    pub format: ValidFormat,
+    /// Weigh color by alpha during the BC1/BC3 cluster fit; ignored for the
+    /// other formats.
+    pub weigh_color_by_alpha: bool,
+    pub generate_mipmaps: bool,
+    pub mipmap_filter: MipmapFilter,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self {
+            input_format: None,
+            format: ValidFormat::Bc3,
+            weigh_color_by_alpha: false,
+            generate_mipmaps: true,
+            mipmap_filter: MipmapFilter::CatmullRom,
+        }
+    }
+}
+
+impl EncodeOptions {
+    /// The `ltk_texture` options these translate to.
+    pub fn texture_options(&self) -> ltk_texture::tex::EncodeOptions {
+        ltk_texture::tex::EncodeOptions {
+            format: self.format.to_encode_format(self.weigh_color_by_alpha),
+            generate_mipmaps: self.generate_mipmaps,
+            mipmap_filter: self.mipmap_filter,
+        }
+    }
+}
+
+/// Encode the image at `input` (`-` for stdin) and write the texture to
+/// `output` (`-` for stdout).
+pub fn encode_file(input: &str, output: &str, options: &EncodeOptions) -> Result<()> {
+    let image = load_image(input, options.input_format)?;
+    write_tex(&encode_image(&image, options)?, output)
+}
+
+pub fn encode_image(image: &RgbaImage, options: &EncodeOptions) -> Result<Tex> {
+    Ok(Tex::encode_rgba_image(image, options.texture_options())?)
+}
+
+/// Encode six face images (+X, -X, +Y, -Y, +Z, -Z order) or one
+/// horizontal-cross image into a cubemap.
+pub fn encode_cubemap(images: &[RgbaImage], options: &EncodeOptions) -> Result<Tex> {
+    let faces = match images {
+        [cross] => &layers::split_cross(cross)?,
+        faces => faces,
+    };
+    if faces.len() != 6 {
+        bail!(
+            "a cubemap needs six face images or one cross-layout image, got {}",
+            images.len()
+        );
+    }
+    layers::encode_cubemap(faces, &options.texture_options())
+}
+
+/// Write `tex` to `output` (`-` for stdout), atomically for files.
+pub fn write_tex(tex: &Tex, output: &str) -> Result<()> {
+    let mut writer = create_output(output)?;
+    tex.write(&mut writer)?;
+    writer.commit()
+}
+
+/// Load the input as an RGBA8 image. DDS inputs are decoded through `ltk_texture`
+/// (top mip only), since the `image` crate cannot read block-compressed DDS.
+///
+/// The image type comes from `input_format`, else the extension, else the content
+/// (the only option for stdin).
+pub fn load_image(input: &str, input_format: Option<ImageFormat>) -> Result<RgbaImage> {
+    let format = input_format.or_else(|| ImageFormat::from_path(input).ok());
+    let bytes = read_input(input)?;
+
+    if bytes.starts_with(b"TEX\0") {
+        bail!("input is already a .tex texture: {input} (use `transcode` to re-encode it)");
+    }
+    if format == Some(ImageFormat::Dds) || bytes.starts_with(b"DDS ") {
+        let dds = Dds::from_reader(&mut bytes.as_slice())?;
+        let surface = dds.decode_mipmap(0).map_err(DecompressError::from)?;
+        return Ok(surface.into_image().map_err(ToImageError::from)?);
+    }
+
+    let image = match format {
+        Some(format) => image::load_from_memory_with_format(&bytes, format)?,
+        None => image::load_from_memory(&bytes)?,
+    };
+    Ok(image.to_rgba8())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::FACE_SUFFIXES;
+
+    #[test]
+    fn encodes_cubemaps_from_faces_or_a_cross() {
+        let options = EncodeOptions {
+            format: ValidFormat::Bgra8,
+            ..EncodeOptions::default()
+        };
+        let faces = vec![RgbaImage::new(4, 4); FACE_SUFFIXES.len()];
+        let tex = encode_cubemap(&faces, &options).unwrap();
+        assert_eq!((tex.width, tex.height, tex.mip_count), (4, 4, 3));
+
+        let cross = layers::compose_cross(&faces).unwrap();
+        assert_eq!(encode_cubemap(&[cross], &options).unwrap().width, 4);
+        assert!(matches!(
+            encode_cubemap(&faces[..5], &options),
+            Err(crate::Error::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+
+#[cfg(feature = "convert")]
+use crate::batch::BatchError;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Everything that can go wrong in the pipeline. The wrapped library errors
+/// are kept as they are, so callers can tell e.g. a truncated texture
+/// ([`Error::Decode`]) from a missing file ([`Error::Io`]).
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a file, stdin or stdout failed.
+    Io(std::io::Error),
+    /// The input is not a readable `.tex` (or `.dds`) file.
+    Read(ltk_texture::ReadError),
+    /// A texture's pixel data could not be decoded.
+    Decode(ltk_texture::tex::DecodeErr),
+    /// A DDS input's pixel data could not be decoded.
+    Decompress(ltk_texture::DecompressError),
+    /// Decoded pixels could not be turned into an RGBA image.
+    ToImage(ltk_texture::ToImageError),
+    /// An image could not be encoded to a texture format.
+    Encode(ltk_texture::tex::EncodeError),
+    /// An input or output image (PNG, TGA, ...) could not be read or written.
+    Image(image::ImageError),
+    /// A DDS output could not be created or written.
+    #[cfg(feature = "convert")]
+    Dds(Box<dyn std::error::Error + Send + Sync>),
+    /// The inputs or options don't fit together, e.g. five cubemap faces or a
+    /// mip level the texture doesn't have.
+    Invalid(String),
+    /// Some jobs of a batch failed.
+    #[cfg(feature = "convert")]
+    Batch(BatchError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => err.fmt(f),
+            Error::Read(err) => err.fmt(f),
+            Error::Decode(err) => err.fmt(f),
+            Error::Decompress(err) => err.fmt(f),
+            Error::ToImage(err) => err.fmt(f),
+            Error::Encode(err) => err.fmt(f),
+            Error::Image(err) => err.fmt(f),
+            #[cfg(feature = "convert")]
+            Error::Dds(err) => err.fmt(f),
+            Error::Invalid(message) => f.write_str(message),
+            #[cfg(feature = "convert")]
+            Error::Batch(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            // Display already shows the wrapped error's message, so skip it
+            // and continue with its own cause.
+            Error::Io(err) => err.source(),
+            Error::Read(err) => err.source(),
+            Error::Decode(err) => err.source(),
+            Error::Decompress(err) => err.source(),
+            Error::ToImage(err) => err.source(),
+            Error::Encode(err) => err.source(),
+            Error::Image(err) => err.source(),
+            #[cfg(feature = "convert")]
+            Error::Dds(err) => err.source(),
+            Error::Invalid(_) => None,
+            #[cfg(feature = "convert")]
+            Error::Batch(_) => None,
+        }
+    }
+}
+
+macro_rules! from {
+    ($($variant:ident($error:ty)),* $(,)?) => {
+        $(impl From<$error> for Error {
+            fn from(err: $error) -> Self {
+                Error::$variant(err)
+            }
+        })*
+    };
+}
+
+from! {
+    Io(std::io::Error),
+    Read(ltk_texture::ReadError),
+    Decode(ltk_texture::tex::DecodeErr),
+    Decompress(ltk_texture::DecompressError),
+    ToImage(ltk_texture::ToImageError),
+    Encode(ltk_texture::tex::EncodeError),
+    Image(image::ImageError),
+}
+
+#[cfg(feature = "convert")]
+from! {
+    Batch(BatchError),
+}
+
+#[cfg(feature = "convert")]
+impl From<image_dds::CreateDdsError> for Error {
+    fn from(err: image_dds::CreateDdsError) -> Self {
+        Error::Dds(err.into())
+    }
+}
+
+#[cfg(feature = "convert")]
+impl From<image_dds::ddsfile::Error> for Error {
+    fn from(err: image_dds::ddsfile::Error) -> Self {
+        Error::Dds(err.into())
+    }
+}
+
+/// `return Err(Error::Invalid(format!(...)))`.
+macro_rules! bail {
+    ($($arg:tt)*) => {
+        return Err($crate::Error::Invalid(format!($($arg)*)))
+    };
+}
+
+pub(crate) use bail;
@@ -0,0 +1,132 @@
+//! Texture formats the pipeline encodes to, image formats it decodes to, and
+//! the parsers that turn their names (as typed on a command line) into them.
+
+use ltk_texture::tex::{EncodeFormat, Format, MipmapFilter};
+
+/// Display name of a TEX format, as shown by the Explorer handlers and the
+/// `view`, `diff`, `montage` and `dedupe` commands.
+pub fn format_name(format: Format) -> &'static str {
+    match format {
+        Format::Etc1 => "ETC1",
+        Format::Etc2Eac => "ETC2/EAC",
+        Format::Bc1 => "BC1",
+        Format::Bc3 => "BC3",
+        Format::Bc7 => "BC7",
+        Format::Bc5Snorm => "BC5 (snorm)",
+        Format::Bgra8 => "BGRA8",
+        Format::Rgba16Float => "RGBA16F",
+        Format::Rgba32Float => "RGBA32F",
+    }
+}
+
+/// A texture format the pipeline can encode to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidFormat {
+    Bc1,
+    Bc3,
+    Bc7,
+    Bgra8,
+    Rgba16Float,
+    Rgba32Float,
+}
+
+impl ValidFormat {
+    /// The encodable format a texture is stored in, if any.
+    pub fn of(format: Format) -> Option<Self> {
+        match format {
+            Format::Bc1 => Some(ValidFormat::Bc1),
+            Format::Bc3 => Some(ValidFormat::Bc3),
+            Format::Bc7 => Some(ValidFormat::Bc7),
+            Format::Bgra8 => Some(ValidFormat::Bgra8),
+            Format::Rgba16Float => Some(ValidFormat::Rgba16Float),
+            Format::Rgba32Float => Some(ValidFormat::Rgba32Float),
+            Format::Etc1 | Format::Etc2Eac | Format::Bc5Snorm => None,
+        }
+    }
+
+    /// Name as accepted by `-f/--format`.
+    pub fn name(self) -> &'static str {
+        match self {
+            ValidFormat::Bc1 => "bc1",
+            ValidFormat::Bc3 => "bc3",
+            ValidFormat::Bc7 => "bc7",
+            ValidFormat::Bgra8 => "bgra8",
+            ValidFormat::Rgba16Float => "rgba16f",
+            ValidFormat::Rgba32Float => "rgba32f",
+        }
+    }
+
+    /// Build the `ltk_texture` encode format, applying any format-specific options.
+    ///
+    /// `weigh_color_by_alpha` only affects the BC1/BC3 cluster fit; it is ignored
+    /// for the other formats.
+    pub fn to_encode_format(self, weigh_color_by_alpha: bool) -> EncodeFormat {
+        match self {
+            ValidFormat::Bc1 => EncodeFormat::Bc1 {
+                weigh_colour_by_alpha: weigh_color_by_alpha,
+            },
+            ValidFormat::Bc3 => EncodeFormat::Bc3 {
+                weigh_colour_by_alpha: weigh_color_by_alpha,
+            },
+            ValidFormat::Bc7 => EncodeFormat::Bc7,
+            ValidFormat::Bgra8 => EncodeFormat::Bgra8,
+            ValidFormat::Rgba16Float => EncodeFormat::Rgba16Float,
+            ValidFormat::Rgba32Float => EncodeFormat::Rgba32Float,
+        }
+    }
+}
+
+/// Output image format for `decode` when no explicit output path is given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum DecodeOutputFormat {
+    /// PNG image
+    #[default]
+    Png,
+    /// Uncompressed RGBA8 DDS (top mip only)
+    Dds,
+}
+
+impl DecodeOutputFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            DecodeOutputFormat::Png => "png",
+            DecodeOutputFormat::Dds => "dds",
+        }
+    }
+}
+
+pub fn parse_mipmap_filter(s: &str) -> Result<MipmapFilter, String> {
+    match s.to_lowercase().as_str() {
+        "nearest" => Ok(MipmapFilter::Nearest),
+        "triangle" => Ok(MipmapFilter::Triangle),
+        "catmullrom" => Ok(MipmapFilter::CatmullRom),
+        "lanczos3" => Ok(MipmapFilter::Lanczos3),
+        _ => Err(format!(
+            "Invalid mipmap filter: {}. Valid options: nearest, triangle, catmullrom, lanczos3",
+            s
+        )),
+    }
+}
+
+pub fn parse_input_format(s: &str) -> Result<image::ImageFormat, String> {
+    image::ImageFormat::from_extension(s.trim_start_matches('.')).ok_or_else(|| {
+        format!("Unknown input image type: {s}. Use a file extension such as png, tga or dds")
+    })
+}
+
+pub fn parse_format(s: &str) -> Result<ValidFormat, String> {
+    match s.to_lowercase().as_str() {
+        "bc1" => Ok(ValidFormat::Bc1),
+        "bc3" => Ok(ValidFormat::Bc3),
+        "bc7" => Ok(ValidFormat::Bc7),
+        "bgra8" => Ok(ValidFormat::Bgra8),
+        "rgba16f" | "rgba16float" => Ok(ValidFormat::Rgba16Float),
+        "rgba32f" | "rgba32float" => Ok(ValidFormat::Rgba32Float),
+        _ => Err(format!(
+            "Invalid format: {}. Valid options: bc1, bc3, bc7, bgra8, rgba16f, rgba32f \
+             (ETC1, ETC2 and BC5 are not supported for encoding)",
+            s
+        )),
+    }
+}
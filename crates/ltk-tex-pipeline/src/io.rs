@@ -0,0 +1,153 @@
+//! Inputs and outputs: files, or stdin/stdout for `-`, with outputs written
+//! atomically.
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::Result;
+
+/// Path placeholder for stdin (as an input) or stdout (as an output).
+pub const STDIO: &str = "-";
+
+pub fn is_stdio(path: impl AsRef<Path>) -> bool {
+    path.as_ref().as_os_str() == STDIO
+}
+
+/// Open an input file, or stdin for `-`.
+pub fn open_input(input: &str) -> Result<Box<dyn BufRead>> {
+    if is_stdio(input) {
+        Ok(Box::new(io::stdin().lock()))
+    } else {
+        Ok(Box::new(BufReader::new(File::open(input)?)))
+    }
+}
+
+/// Read a whole input file, or all of stdin for `-`.
+pub fn read_input(input: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    open_input(input)?.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Create an output file (and its parent directories), or stdout for `-`.
+///
+/// Nothing reaches the destination until [`Output::commit`].
+pub fn create_output(output: &str) -> Result<Output> {
+    if is_stdio(output) {
+        return Ok(Output::Stdout(io::stdout().lock()));
+    }
+    Ok(Output::File(OutputFile::create(Path::new(output))?))
+}
+
+/// Where a command writes its result: stdout, or an [`OutputFile`].
+pub enum Output {
+    Stdout(io::StdoutLock<'static>),
+    File(OutputFile),
+}
+
+impl Output {
+    /// Flush everything written and, for a file, move it into place.
+    pub fn commit(self) -> Result<()> {
+        match self {
+            Output::Stdout(mut stdout) => Ok(stdout.flush()?),
+            Output::File(file) => file.commit(),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Stdout(stdout) => stdout.write(buf),
+            Output::File(file) => file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout(stdout) => stdout.flush(),
+            Output::File(file) => file.flush(),
+        }
+    }
+}
+
+/// A file written atomically: data goes to a temporary file in the destination
+/// folder, which replaces the destination only once [`Self::commit`] succeeds.
+/// Dropped without a commit (e.g. after an error), the temporary file is removed
+/// and an existing destination is left untouched.
+pub struct OutputFile {
+    file: BufWriter<tempfile::NamedTempFile>,
+    path: PathBuf,
+}
+
+impl OutputFile {
+    /// Start writing `path`, creating its parent directories.
+    pub fn create(path: &Path) -> Result<Self> {
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        fs::create_dir_all(dir)?;
+
+        let mut builder = tempfile::Builder::new();
+        builder.prefix(".ltk-tex-").suffix(".tmp");
+        // Temporary files are owner-only by default; use the usual file mode
+        // (minus the umask) since this one becomes the output.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            builder.permissions(fs::Permissions::from_mode(0o666));
+        }
+        Ok(Self {
+            file: BufWriter::new(builder.tempfile_in(dir)?),
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Flush and move the file into place, replacing any existing file.
+    pub fn commit(self) -> Result<()> {
+        let file = self.file.into_inner().map_err(|e| e.into_error())?;
+        file.as_file().sync_all()?;
+        file.persist(&self.path).map_err(|err| err.error)?;
+        Ok(())
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Seek for OutputFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_file_replaces_destination_only_on_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("out.tex");
+
+        let mut file = OutputFile::create(&path).unwrap();
+        file.write_all(b"first").unwrap();
+        file.commit().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"first");
+
+        let mut file = OutputFile::create(&path).unwrap();
+        file.write_all(b"partial").unwrap();
+        drop(file);
+        assert_eq!(fs::read(&path).unwrap(), b"first");
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+    }
+}
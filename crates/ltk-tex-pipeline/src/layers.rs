@@ -0,0 +1,276 @@
+//! Multi-layer textures: cubemaps (six faces) and volume textures (z-slices).
+//!
+//! TEX cubemaps are assumed to follow the D3D face order (+X, -X, +Y, -Y, +Z, -Z)
+//! with the faces of each mip level stored back to back, the same way volume
+//! slices are (see `texdata`). The cross layout used for import/export is the
+//! usual horizontal cross:
+//!
+//! ```text
+//!         +Y
+//!     -X  +Z  +X  -Z
+//!         -Y
+//! ```
+
+use image::RgbaImage;
+use image::imageops::FilterType;
+use ltk_texture::Tex;
+use ltk_texture::tex::{EncodeOptions, MipmapFilter, ResourceType, TextureFlags, encode_rgba};
+
+use crate::Result;
+use crate::error::bail;
+use crate::texdata::{self, CUBEMAP_FACES, Header};
+
+/// File-name suffixes of the cubemap faces, in storage order.
+pub const FACE_SUFFIXES: [&str; 6] = ["px", "nx", "py", "ny", "pz", "nz"];
+
+/// Cell (column, row) of each face in the 4x3 horizontal cross, in storage order.
+const CROSS_CELLS: [(u32, u32); 6] = [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)];
+
+/// How `decode` handles the faces/slices of cubemap and volume textures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum LayerMode {
+    /// Only the first face/slice (same as a 2D texture)
+    #[default]
+    First,
+    /// One image per face/slice, suffixed `_px`.. for cubemap faces and `_sliceN` for volume slices
+    Split,
+    /// A single horizontal-cross image (cubemaps only)
+    Cross,
+    /// A single DDS holding every face/slice as a cubemap or volume texture (DDS output only)
+    Array,
+}
+
+/// Whether `tex` stores more than one image per mip level.
+pub fn is_layered(tex: &Tex) -> bool {
+    Header::of(tex).layer_count(0) > 1
+}
+
+/// File-name suffix for `layer` of a texture with `resource_type`.
+pub fn layer_suffix(resource_type: ResourceType, layer: u32) -> String {
+    match resource_type {
+        ResourceType::Cubemap => FACE_SUFFIXES[layer as usize].to_string(),
+        _ => format!("slice{layer}"),
+    }
+}
+
+/// Decode every face/slice of mip `level` to RGBA8, in storage order.
+pub fn decode_layers(tex: &Tex, level: u32) -> Result<Vec<RgbaImage>> {
+    let header = Header::of(tex);
+    texdata::check_level(&header, level)?;
+    (0..header.layer_count(level))
+        .map(|layer| {
+            let layer = texdata::layer_tex(tex, level, layer)?;
+            Ok(layer.decode_mipmap(0)?.into_rgba_image()?)
+        })
+        .collect()
+}
+
+/// Decode every face/slice of mip `level` to RGBA8; a single image for 2D textures.
+pub fn decode_level(tex: &Tex, level: u32) -> Result<Vec<RgbaImage>> {
+    if is_layered(tex) {
+        return decode_layers(tex, level);
+    }
+    texdata::check_level(&Header::of(tex), level)?;
+    Ok(vec![tex.decode_mipmap(level)?.into_rgba_image()?])
+}
+
+/// Decode face/slice `layer` of mip `level` to RGBA8 (`layer` must be 0 for 2D
+/// textures).
+pub fn decode_layer(tex: &Tex, level: u32, layer: u32) -> Result<RgbaImage> {
+    if is_layered(tex) {
+        let layer = texdata::layer_tex(tex, level, layer)?;
+        return Ok(layer.decode_mipmap(0)?.into_rgba_image()?);
+    }
+    texdata::check_level(&Header::of(tex), level)?;
+    if layer > 0 {
+        bail!("layer {layer} out of range (2D textures have a single layer)");
+    }
+    Ok(tex.decode_mipmap(level)?.into_rgba_image()?)
+}
+
+/// Stack equally sized images vertically, first on top.
+pub fn stack(images: &[RgbaImage]) -> RgbaImage {
+    let (width, height) = images[0].dimensions();
+    let mut stacked = RgbaImage::new(width, height * images.len() as u32);
+    for (i, image) in images.iter().enumerate() {
+        image::imageops::replace(&mut stacked, image, 0, (i as u32 * height) as i64);
+    }
+    stacked
+}
+
+/// Lay six equally sized faces out as a horizontal cross.
+pub fn compose_cross(faces: &[RgbaImage]) -> Result<RgbaImage> {
+    let [first, ..] = faces else {
+        bail!("no faces to compose");
+    };
+    if faces.len() != CUBEMAP_FACES as usize {
+        bail!("a cross layout needs 6 faces, got {}", faces.len());
+    }
+
+    let size = first.width();
+    let mut cross = RgbaImage::new(size * 4, size * 3);
+    for (face, (col, row)) in faces.iter().zip(CROSS_CELLS) {
+        image::imageops::replace(&mut cross, face, (col * size) as i64, (row * size) as i64);
+    }
+    Ok(cross)
+}
+
+/// Cut a horizontal-cross image back into its six faces, in storage order.
+pub fn split_cross(cross: &RgbaImage) -> Result<Vec<RgbaImage>> {
+    let (width, height) = cross.dimensions();
+    if width == 0 || width % 4 != 0 || width / 4 * 3 != height {
+        bail!("a cross-layout cubemap must be 4:3 with square faces, got {width}x{height}");
+    }
+
+    let size = width / 4;
+    Ok(CROSS_CELLS
+        .iter()
+        .map(|&(col, row)| {
+            image::imageops::crop_imm(cross, col * size, row * size, size, size).to_image()
+        })
+        .collect())
+}
+
+/// Encode six square faces (storage order) into a cubemap TEX.
+///
+/// Each face gets its own mip chain; per level, the faces are stored back to back.
+pub fn encode_cubemap(faces: &[RgbaImage], options: &EncodeOptions) -> Result<Tex> {
+    let [first, ..] = faces else {
+        bail!("no cubemap faces given");
+    };
+    if faces.len() != CUBEMAP_FACES as usize {
+        bail!("a cubemap needs exactly 6 faces, got {}", faces.len());
+    }
+    let (width, height) = first.dimensions();
+    if width != height {
+        bail!("cubemap faces must be square, got {width}x{height}");
+    }
+    if let Some(face) = faces.iter().find(|f| f.dimensions() != (width, height)) {
+        bail!(
+            "cubemap faces must all be {width}x{height}, found one that is {}x{}",
+            face.width(),
+            face.height()
+        );
+    }
+
+    let Ok(size) = u16::try_from(width) else {
+        bail!(
+            "cubemap faces must be at most {} pixels wide, got {width}",
+            u16::MAX
+        );
+    };
+    let header = Header {
+        width: size,
+        height: size,
+        depth: 1,
+        format: options.format.into(),
+        resource_type: ResourceType::Cubemap,
+        flags: if options.generate_mipmaps {
+            TextureFlags::HasMipMaps
+        } else {
+            TextureFlags::empty()
+        },
+    };
+
+    // Build every face's mip chain up front (largest first), then store the
+    // levels smallest first with the six faces of each level back to back.
+    let chains = faces
+        .iter()
+        .map(|face| mip_chain(face, header.mip_count(), options.mipmap_filter))
+        .collect::<Vec<_>>();
+
+    let mut data = Vec::with_capacity(header.expected_len());
+    for level in (0..header.mip_count() as usize).rev() {
+        for chain in &chains {
+            let mip = &chain[level];
+            data.extend(encode_rgba(
+                mip.width(),
+                mip.height(),
+                mip.as_raw(),
+                options,
+            )?);
+        }
+    }
+
+    texdata::assemble(&header, &data)
+}
+
+/// `image` followed by `mip_count - 1` successively halved copies of it.
+pub fn mip_chain(image: &RgbaImage, mip_count: u32, filter: MipmapFilter) -> Vec<RgbaImage> {
+    let mut chain = vec![image.clone()];
+    for level in 1..mip_count {
+        let prev = &chain[level as usize - 1];
+        let (w, h) = (
+            (image.width() >> level).max(1),
+            (image.height() >> level).max(1),
+        );
+        chain.push(image::imageops::resize(prev, w, h, filter_type(filter)));
+    }
+    chain
+}
+
+fn filter_type(filter: MipmapFilter) -> FilterType {
+    match filter {
+        MipmapFilter::Nearest => FilterType::Nearest,
+        MipmapFilter::Triangle => FilterType::Triangle,
+        MipmapFilter::CatmullRom => FilterType::CatmullRom,
+        MipmapFilter::Lanczos3 => FilterType::Lanczos3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ltk_texture::tex::EncodeFormat;
+
+    fn solid_faces(size: u32) -> Vec<RgbaImage> {
+        (0..6u8)
+            .map(|i| RgbaImage::from_pixel(size, size, image::Rgba([i * 40, 0, 0, 255])))
+            .collect()
+    }
+
+    #[test]
+    fn cross_roundtrips_faces() {
+        let faces = solid_faces(2);
+        let cross = compose_cross(&faces).unwrap();
+        assert_eq!(cross.dimensions(), (8, 6));
+        assert_eq!(split_cross(&cross).unwrap(), faces);
+    }
+
+    #[test]
+    fn encoded_cubemap_decodes_every_face_and_mip() {
+        let faces = solid_faces(4);
+        let options = EncodeOptions::new(EncodeFormat::Bgra8).with_mipmaps();
+        let tex = encode_cubemap(&faces, &options).unwrap();
+        assert_eq!(tex.resource_type, ResourceType::Cubemap);
+        assert_eq!(tex.mip_count, 3);
+
+        for level in 0..tex.mip_count {
+            let decoded = decode_layers(&tex, level).unwrap();
+            assert_eq!(decoded.len(), 6);
+            for (i, face) in decoded.iter().enumerate() {
+                assert_eq!(face.width(), 4 >> level);
+                assert_eq!(face.get_pixel(0, 0).0, [i as u8 * 40, 0, 0, 255]);
+            }
+        }
+        assert!(decode_layers(&tex, tex.mip_count).is_err());
+    }
+
+    #[test]
+    fn decode_level_and_layer_treat_2d_textures_as_one_layer() {
+        let image = RgbaImage::from_pixel(4, 2, image::Rgba([9, 0, 0, 255]));
+        let options = EncodeOptions::new(EncodeFormat::Bgra8).with_mipmaps();
+        let tex = Tex::encode_rgba_image(&image, options).unwrap();
+
+        assert_eq!(decode_level(&tex, 1).unwrap()[0].dimensions(), (2, 1));
+        assert_eq!(decode_layer(&tex, 0, 0).unwrap(), image);
+        assert!(decode_level(&tex, tex.mip_count).is_err());
+        assert!(decode_layer(&tex, 0, 1).is_err());
+
+        let faces = solid_faces(2);
+        let stacked = stack(&faces);
+        assert_eq!(stacked.dimensions(), (2, 12));
+        assert_eq!(stacked.get_pixel(0, 10).0, [200, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,43 @@
+//! The conversion pipeline behind `ltk-tex-utils`, for tools that want to
+//! convert League `.tex` textures without shelling out to the CLI.
+//!
+//! - [`encode`]: images (PNG, TGA, DDS, ...) to `.tex`, including cubemaps.
+//! - [`decode`]: `.tex` to RGBA images, PNG or DDS files, with every way of
+//!   exporting cubemap faces and volume slices.
+//! - [`batch`]: run a conversion over many files, with overwrite policies,
+//!   failure limits, progress callbacks and JSON/JUnit reports.
+//! - [`texdata`] and [`layers`]: the raw payload layout and the multi-layer
+//!   helpers the rest is built on.
+//!
+//! Everything returns [`Error`]. Without the default `convert` feature only
+//! in-memory decoding is built ([`decode::decode_rgba`],
+//! [`decode::decode_with_meta`], [`layers`], [`texdata`]), which is what the
+//! Explorer shell handler uses.
+//!
+//! ```no_run
+//! use ltk_tex_pipeline::encode::{EncodeOptions, encode_file};
+//! use ltk_tex_pipeline::format::ValidFormat;
+//!
+//! let options = EncodeOptions {
+//!     format: ValidFormat::Bc7,
+//!     ..EncodeOptions::default()
+//! };
+//! encode_file("icon.png", "icon.tex", &options)?;
+//! # Ok::<(), ltk_tex_pipeline::Error>(())
+//! ```
+
+#[cfg(feature = "convert")]
+pub mod batch;
+pub mod decode;
+#[cfg(feature = "convert")]
+pub mod encode;
+mod error;
+pub mod format;
+#[cfg(feature = "convert")]
+pub mod io;
+pub mod layers;
+#[cfg(feature = "convert")]
+pub mod report;
+pub mod texdata;
+
+pub use error::{Error, Result};
@@ -0,0 +1,182 @@
+//! Machine-readable batch reports (`--report`): JSON, or JUnit XML so failed
+//! textures show up as failed tests in CI dashboards.
+
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::Result;
+use crate::io::OutputFile;
+
+/// Report file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum ReportFormat {
+    Json,
+    Junit,
+}
+
+impl ReportFormat {
+    /// JUnit for `.xml` paths, JSON otherwise.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("xml") => Self::Junit,
+            _ => Self::Json,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Ok,
+    Failed,
+    /// Not attempted because the batch was aborted.
+    Skipped,
+}
+
+/// Outcome of one job of a batch.
+#[derive(Debug, Serialize)]
+pub struct JobReport {
+    pub input: String,
+    pub output: String,
+    pub status: JobStatus,
+    /// Error message chain, outermost first (empty on success).
+    pub errors: Vec<String>,
+    pub elapsed_ms: f64,
+    pub input_size: Option<u64>,
+    pub output_size: Option<u64>,
+    pub format: String,
+}
+
+/// Outcome of a whole batch.
+#[derive(Debug, Serialize)]
+pub struct BatchReport {
+    pub command: String,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub elapsed_ms: f64,
+    pub jobs: Vec<JobReport>,
+}
+
+impl BatchReport {
+    pub fn write(&self, path: &Path, format: ReportFormat) -> Result<()> {
+        let mut file = OutputFile::create(path)?;
+        match format {
+            ReportFormat::Json => {
+                serde_json::to_writer_pretty(&mut file, self).map_err(std::io::Error::from)?;
+                file.write_all(b"\n")?;
+            }
+            ReportFormat::Junit => file.write_all(self.to_junit().as_bytes())?,
+        }
+        file.commit()
+    }
+
+    fn to_junit(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            xml,
+            "<testsuites name=\"ltk-tex-utils\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+            self.total,
+            self.failed,
+            self.skipped,
+            self.elapsed_ms / 1000.0
+        );
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+            escape(&self.command),
+            self.total,
+            self.failed,
+            self.skipped,
+            self.elapsed_ms / 1000.0
+        );
+        for job in &self.jobs {
+            let _ = write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}.{}\" time=\"{:.3}\">",
+                escape(&job.input),
+                escape(&self.command),
+                escape(&job.format),
+                job.elapsed_ms / 1000.0
+            );
+            match job.status {
+                JobStatus::Ok => {}
+                JobStatus::Failed => {
+                    let message = job.errors.first().map_or("", String::as_str);
+                    let _ = write!(
+                        xml,
+                        "\n      <failure message=\"{}\">{}</failure>\n    ",
+                        escape(message),
+                        escape(&job.errors.join("\n"))
+                    );
+                }
+                JobStatus::Skipped => xml.push_str("\n      <skipped/>\n    "),
+            }
+            xml.push_str("</testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn junit_marks_failures_and_escapes() {
+        let job = |input: &str, errors: Vec<String>| JobReport {
+            input: input.to_string(),
+            output: "out.tex".to_string(),
+            status: if errors.is_empty() {
+                JobStatus::Ok
+            } else {
+                JobStatus::Failed
+            },
+            errors,
+            elapsed_ms: 1.0,
+            input_size: Some(1),
+            output_size: None,
+            format: "bc3".to_string(),
+        };
+        let report = BatchReport {
+            command: "encode".to_string(),
+            total: 2,
+            succeeded: 1,
+            failed: 1,
+            skipped: 0,
+            elapsed_ms: 2.0,
+            jobs: vec![
+                job("a.png", Vec::new()),
+                job("b&c.png", vec!["bad <data>".to_string()]),
+            ],
+        };
+        let xml = report.to_junit();
+        assert!(xml.contains("tests=\"2\" failures=\"1\" skipped=\"0\""));
+        assert!(xml.contains(
+            "<testcase name=\"a.png\" classname=\"encode.bc3\" time=\"0.001\"></testcase>"
+        ));
+        assert!(xml.contains("name=\"b&amp;c.png\""));
+        assert!(xml.contains("<failure message=\"bad &lt;data&gt;\">"));
+    }
+}
@@ -0,0 +1,394 @@
+//! Raw TEX payload layout: where each mip level - and each cubemap face or volume
+//! slice within it - lives in the data that follows the 12-byte header.
+//!
+//! `ltk_texture::Tex` keeps its payload private and only decodes 2D and volume
+//! surfaces, so commands that need to slice, reassemble or validate the raw bytes
+//! go through these helpers: the payload is read back out with `Tex::write`, and new
+//! textures are built by serializing a header and parsing it with `Tex::from_reader`.
+//!
+//! Layout (matching `Tex::decode_mipmap_slice`): mip levels are stored smallest
+//! first; within a level, every layer (cubemap face or volume slice) is stored
+//! back to back.
+
+use std::ops::Range;
+
+use ltk_texture::Tex;
+use ltk_texture::tex::{Format, ResourceType, TextureFlags};
+
+use crate::error::bail;
+use crate::layers;
+use crate::{Error, Result};
+
+/// Size of the TEX header (magic included) that precedes the payload.
+pub const HEADER_LEN: usize = 12;
+
+/// Number of faces in a cubemap.
+pub const CUBEMAP_FACES: u32 = 6;
+
+/// Header fields of a TEX file, used to assemble new textures from raw data.
+#[derive(Debug, Clone, Copy)]
+pub struct Header {
+    pub width: u16,
+    pub height: u16,
+    pub depth: u8,
+    pub format: Format,
+    pub resource_type: ResourceType,
+    pub flags: TextureFlags,
+}
+
+impl Header {
+    pub fn of(tex: &Tex) -> Self {
+        Self {
+            width: tex.width,
+            height: tex.height,
+            depth: tex.depth,
+            format: tex.format,
+            resource_type: tex.resource_type,
+            flags: tex.flags,
+        }
+    }
+
+    pub fn has_mipmaps(&self) -> bool {
+        self.flags.contains(TextureFlags::HasMipMaps)
+    }
+
+    /// Mip count implied by the header, computed the same way `Tex::from_reader` does.
+    pub fn mip_count(&self) -> u32 {
+        if self.has_mipmaps() {
+            (self.width.max(self.height).max(self.depth as u16).max(1) as u32).ilog2() + 1
+        } else {
+            1
+        }
+    }
+
+    /// Dimensions of mip `level` (halved per level, floored at 1).
+    pub fn mip_dimensions(&self, level: u32) -> (u32, u32) {
+        (
+            (self.width as u32 >> level).max(1),
+            (self.height as u32 >> level).max(1),
+        )
+    }
+
+    /// Number of layers stored per mip `level`: six faces for cubemaps, the mip's
+    /// z-slice count for volume textures, and one otherwise.
+    pub fn layer_count(&self, level: u32) -> u32 {
+        match self.resource_type {
+            ResourceType::Cubemap => CUBEMAP_FACES,
+            ResourceType::VolumeTexture => ((self.depth as u32) >> level).max(1),
+            ResourceType::Texture | ResourceType::Surface => 1,
+        }
+    }
+
+    /// Size in bytes of a single layer of mip `level`.
+    pub fn layer_len(&self, level: u32) -> usize {
+        let (w, h) = self.mip_dimensions(level);
+        let (block_w, block_h) = self.format.block_size();
+        (w as usize).div_ceil(block_w)
+            * (h as usize).div_ceil(block_h)
+            * self.format.bytes_per_block()
+    }
+
+    /// Size in bytes of mip `level`, all layers included.
+    pub fn mip_len(&self, level: u32) -> usize {
+        self.layer_len(level) * self.layer_count(level) as usize
+    }
+
+    /// Payload length a well-formed texture with this header has.
+    pub fn expected_len(&self) -> usize {
+        (0..self.mip_count()).map(|level| self.mip_len(level)).sum()
+    }
+
+    /// Byte range of mip `level` within the payload.
+    pub fn mip_range(&self, level: u32) -> Range<usize> {
+        let start = (level + 1..self.mip_count())
+            .map(|level| self.mip_len(level))
+            .sum::<usize>();
+        start..start + self.mip_len(level)
+    }
+
+    /// Byte range of `layer` of mip `level` within the payload.
+    pub fn layer_range(&self, level: u32, layer: u32) -> Range<usize> {
+        let start = self.mip_range(level).start + layer as usize * self.layer_len(level);
+        start..start + self.layer_len(level)
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&Tex::MAGIC.to_le_bytes());
+        out.extend_from_slice(&self.width.to_le_bytes());
+        out.extend_from_slice(&self.height.to_le_bytes());
+        out.push(self.depth);
+        out.push(self.format.to_u8());
+        out.push(self.resource_type.to_u8());
+        out.push(self.flags.bits());
+    }
+}
+
+/// The raw payload (everything after the header) of `tex`.
+pub fn payload(tex: &Tex) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    // Writing into a Vec cannot fail.
+    tex.write(&mut bytes).unwrap();
+    bytes.split_off(HEADER_LEN)
+}
+
+/// Serialize `header` followed by `data` into the bytes of a `.tex` file.
+pub fn to_bytes(header: &Header, data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN + data.len());
+    header.write(&mut bytes);
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+/// Build a [`Tex`] from a header and raw payload.
+pub fn assemble(header: &Header, data: &[u8]) -> Result<Tex> {
+    let bytes = to_bytes(header, data);
+    Ok(Tex::from_reader(&mut bytes.as_slice())?)
+}
+
+/// Fail unless the texture has a mip `level`.
+pub fn check_level(header: &Header, level: u32) -> Result<()> {
+    let mip_count = header.mip_count();
+    if level >= mip_count {
+        bail!("mip {level} out of range (the texture has {mip_count} mip level(s))");
+    }
+    Ok(())
+}
+
+/// Extract `layer` of mip `level` as a standalone, mip-less 2D texture, so it can
+/// be decoded with `Tex::decode_mipmap(0)` whatever the source resource type.
+pub fn layer_tex(tex: &Tex, level: u32, layer: u32) -> Result<Tex> {
+    let header = Header::of(tex);
+    check_level(&header, level)?;
+    if layer >= header.layer_count(level) {
+        bail!(
+            "layer {layer} out of range ({} layer(s) at mip {level})",
+            header.layer_count(level)
+        );
+    }
+
+    let payload = payload(tex);
+    let range = header.layer_range(level, layer);
+    let data = payload.get(range.clone()).ok_or_else(|| {
+        Error::Invalid(format!(
+            "texture data is truncated: mip {level} layer {layer} needs bytes {}..{}, have {}",
+            range.start,
+            range.end,
+            payload.len()
+        ))
+    })?;
+
+    let (width, height) = header.mip_dimensions(level);
+    assemble(
+        &Header {
+            width: width as u16,
+            height: height as u16,
+            depth: 1,
+            format: header.format,
+            resource_type: ResourceType::Texture,
+            flags: TextureFlags::empty(),
+        },
+        data,
+    )
+}
+
+/// Keep mip `levels` and below of a texture with mipmaps: they are stored first,
+/// so the new payload is a prefix of the old one.
+pub fn drop_top_mips(source: &Header, payload: &[u8], levels: u32) -> Result<Tex> {
+    let mip_count = source.mip_count();
+    if levels >= mip_count {
+        bail!("cannot drop {levels} mip level(s) of a texture that has {mip_count}");
+    }
+    let (width, height) = source.mip_dimensions(levels);
+    let header = Header {
+        width: width as u16,
+        height: height as u16,
+        depth: ((source.depth as u32) >> levels).max(1) as u8,
+        ..*source
+    };
+    if header.mip_count() != mip_count - levels {
+        bail!(
+            "{}x{} cannot drop {levels} mip level(s) and keep a consistent mip chain",
+            source.width,
+            source.height
+        );
+    }
+    let end = source.mip_range(levels).end;
+    let data = payload.get(..end).ok_or_else(|| {
+        Error::Invalid(format!(
+            "texture data is truncated: needs {end} byte(s), have {}",
+            payload.len()
+        ))
+    })?;
+    assemble(&header, data)
+}
+
+/// Every problem found in the bytes of a `.tex` file; empty if it is sound.
+pub fn verify(bytes: &[u8]) -> Vec<String> {
+    if bytes.len() < HEADER_LEN {
+        return vec![format!(
+            "file is {} byte(s), shorter than the {HEADER_LEN}-byte header",
+            bytes.len()
+        )];
+    }
+    let tex = match Tex::from_reader(&mut &bytes[..]) {
+        Ok(tex) => tex,
+        Err(err) => return vec![format!("invalid header: {err}")],
+    };
+
+    let header = Header::of(&tex);
+    let mut problems = header_problems(&header);
+    if !problems.is_empty() {
+        return problems;
+    }
+
+    let expected = header.expected_len();
+    let found = bytes.len() - HEADER_LEN;
+    let mips = header.mip_count();
+    if found < expected {
+        problems.push(format!(
+            "data is truncated: {:?} {}x{} with {mips} mip(s) needs {expected} byte(s), found {found}",
+            header.format, header.width, header.height
+        ));
+        return problems;
+    }
+    if found > expected {
+        problems.push(format!(
+            "{} byte(s) of trailing garbage after the {expected} byte(s) of texture data",
+            found - expected
+        ));
+    }
+
+    for level in 0..mips {
+        let decoded = if layers::is_layered(&tex) {
+            layers::decode_layers(&tex, level).map(drop)
+        } else {
+            tex.decode_mipmap(level).map(drop).map_err(Error::from)
+        };
+        if let Err(err) = decoded {
+            problems.push(format!("mip {level} does not decode: {err:#}"));
+        }
+    }
+    problems
+}
+
+/// Problems with the header fields alone, which make the payload meaningless.
+fn header_problems(header: &Header) -> Vec<String> {
+    let mut problems = Vec::new();
+    if header.width == 0 || header.height == 0 {
+        problems.push(format!(
+            "invalid dimensions {}x{}",
+            header.width, header.height
+        ));
+    }
+    match header.resource_type {
+        ResourceType::Cubemap if header.width != header.height => problems.push(format!(
+            "cubemap faces must be square, not {}x{}",
+            header.width, header.height
+        )),
+        ResourceType::VolumeTexture if header.depth == 0 => {
+            problems.push("volume texture has a depth of 0".to_string())
+        }
+        _ => {}
+    }
+
+    let (block_w, block_h) = header.format.block_size();
+    if !(header.width as usize).is_multiple_of(block_w)
+        || !(header.height as usize).is_multiple_of(block_h)
+    {
+        problems.push(format!(
+            "{}x{} is not a multiple of the {block_w}x{block_h} block size of {:?}",
+            header.width, header.height, header.format
+        ));
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(resource_type: ResourceType, depth: u8, flags: TextureFlags) -> Header {
+        Header {
+            width: 8,
+            height: 4,
+            depth,
+            format: Format::Bgra8,
+            resource_type,
+            flags,
+        }
+    }
+
+    #[test]
+    fn mip_ranges_are_stored_smallest_first() {
+        let h = header(ResourceType::Texture, 1, TextureFlags::HasMipMaps);
+        assert_eq!(h.mip_count(), 4); // 8x4 -> 4x2 -> 2x1 -> 1x1
+        assert_eq!(h.mip_range(3), 0..4);
+        assert_eq!(h.mip_range(2), 4..12);
+        assert_eq!(h.mip_range(1), 12..44);
+        assert_eq!(h.mip_range(0), 44..172);
+        assert_eq!(h.expected_len(), 172);
+    }
+
+    #[test]
+    fn cubemap_faces_are_stored_per_mip() {
+        let h = header(ResourceType::Cubemap, 1, TextureFlags::empty());
+        assert_eq!(h.layer_count(0), 6);
+        assert_eq!(h.layer_range(0, 0), 0..128);
+        assert_eq!(h.layer_range(0, 5), 640..768);
+        assert_eq!(h.expected_len(), 768);
+    }
+
+    #[test]
+    fn layer_tex_extracts_a_single_face() {
+        let h = header(ResourceType::Cubemap, 1, TextureFlags::empty());
+        let data: Vec<u8> = (0..6u8).flat_map(|face| [face; 128]).collect();
+        let tex = assemble(&h, &data).unwrap();
+
+        let face = layer_tex(&tex, 0, 3).unwrap();
+        assert_eq!((face.width, face.height), (8, 4));
+        assert_eq!(face.resource_type, ResourceType::Texture);
+        assert_eq!(payload(&face), vec![3u8; 128]);
+        assert!(layer_tex(&tex, 0, 6).is_err());
+        assert!(layer_tex(&tex, 1, 0).is_err());
+    }
+
+    #[test]
+    fn sound_texture_has_no_problems() {
+        let h = header(ResourceType::Texture, 1, TextureFlags::HasMipMaps);
+        let bytes = to_bytes(&h, &vec![0; h.expected_len()]);
+        assert_eq!(verify(&bytes), Vec::<String>::new());
+    }
+
+    #[test]
+    fn verify_reports_length_and_alignment_problems() {
+        let h = header(ResourceType::Texture, 1, TextureFlags::HasMipMaps);
+        let truncated = to_bytes(&h, &vec![0; h.expected_len() - 1]);
+        assert!(verify(&truncated)[0].starts_with("data is truncated"));
+
+        let trailing = to_bytes(&h, &vec![0; h.expected_len() + 3]);
+        assert_eq!(
+            verify(&trailing),
+            ["3 byte(s) of trailing garbage after the 172 byte(s) of texture data"]
+        );
+
+        let unaligned = Header {
+            width: 6,
+            format: Format::Bc1,
+            ..h
+        };
+        let bytes = to_bytes(&unaligned, &vec![0; unaligned.expected_len()]);
+        assert!(verify(&bytes)[0].contains("block size"));
+
+        assert!(verify(b"TEX\0")[0].contains("shorter than"));
+    }
+
+    #[test]
+    fn dropping_top_mips_keeps_a_prefix_of_the_payload() {
+        let h = header(ResourceType::Texture, 1, TextureFlags::HasMipMaps);
+        let data: Vec<u8> = (0..h.expected_len()).map(|i| i as u8).collect();
+        let small = drop_top_mips(&h, &data, 2).unwrap();
+        assert_eq!((small.width, small.height), (2, 1));
+        assert_eq!(payload(&small), data[..h.mip_range(2).end]);
+        assert!(drop_top_mips(&h, &data, 4).is_err());
+    }
+}
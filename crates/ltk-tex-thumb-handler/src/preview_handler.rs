@@ -25,7 +25,7 @@ use windows::Win32::UI::WindowsAndMessaging::*;
 use windows::core::*;
 
 use crate::debug::debug_log;
-use crate::image_processing::{TexMeta, decode_tex_with_meta};
+use crate::image_processing::decode_tex_with_meta;
 use crate::raster::{draw_checker, draw_overlay, resize_rgba};
 use crate::utils::{create_premul_hbitmap, read_stream_to_bytes, to_premultiplied_bgra};
 
@@ -203,7 +203,7 @@ impl IPreviewHandler_Impl for CTexPreviewHandler_Impl {
             rgba,
             width: w as i32,
             height: h as i32,
-            lines: build_meta_lines(&meta),
+            lines: meta.lines(),
         });
 
         ensure_window_class();
@@ -287,15 +287,6 @@ impl IPreviewHandlerVisuals_Impl for CTexPreviewHandler_Impl {
     }
 }
 
-fn build_meta_lines(m: &TexMeta) -> Vec<String> {
-    vec![
-        format!("{} x {}", m.width, m.height),
-        format!("Format: {}", m.format),
-        format!("Mips: {}", m.mip_count),
-        format!("Alpha: {}", if m.has_alpha { "yes" } else { "no" }),
-    ]
-}
-
 fn instance() -> HINSTANCE {
     unsafe { HINSTANCE(GetModuleHandleW(PCWSTR::null()).unwrap_or_default().0) }
 }
@@ -426,24 +417,4 @@ mod tests {
         assert!(unknown.cast::<IPreviewHandler>().is_ok());
         assert!(unknown.cast::<IPreviewHandlerVisuals>().is_ok());
     }
-
-    #[test]
-    fn builds_expected_metadata_lines() {
-        let meta = TexMeta {
-            format: "BC7",
-            width: 1024,
-            height: 512,
-            mip_count: 11,
-            has_alpha: true,
-        };
-        assert_eq!(
-            build_meta_lines(&meta),
-            vec![
-                "1024 x 512".to_string(),
-                "Format: BC7".to_string(),
-                "Mips: 11".to_string(),
-                "Alpha: yes".to_string(),
-            ]
-        );
-    }
 }
@@ -2,8 +2,8 @@
 // UTILITIES
 //
 // Generic, non-TEX helpers shared across handlers: IStream I/O, pixel-buffer
-// math (premultiply, scale), and 32bpp DIB creation. Nothing here knows about
-// the .tex format.
+// math (premultiply; scaling lives in `ltk_tex_handler_shared`), and 32bpp DIB
+// creation. Nothing here knows about the .tex format.
 // =============================================================================
 
 use std::ffi::c_void;
@@ -54,27 +54,6 @@ pub fn to_premultiplied_bgra(rgba: &[u8]) -> Vec<u8> {
     out
 }
 
-/// Scale RGBA image to fit within thumbnail size
-pub fn scale_image(src: &[u8], src_w: u32, src_h: u32, cx: u32) -> (Vec<u8>, u32, u32) {
-    let (dst_w, dst_h) = if src_w >= src_h {
-        (cx, (src_h * cx + src_w / 2) / src_w)
-    } else {
-        ((src_w * cx + src_h / 2) / src_h, cx)
-    };
-
-    let mut out = vec![0u8; (dst_w * dst_h * 4) as usize];
-    for y in 0..dst_h {
-        let sy = (y * src_h / dst_h).clamp(0, src_h - 1);
-        for x in 0..dst_w {
-            let sx = (x * src_w / dst_w).clamp(0, src_w - 1);
-            let si = ((sy * src_w + sx) * 4) as usize;
-            let di = ((y * dst_w + x) * 4) as usize;
-            out[di..di + 4].copy_from_slice(&src[si..si + 4]);
-        }
-    }
-    (out, dst_w, dst_h)
-}
-
 /// Create a top-down 32bpp DIB section HBITMAP from already-premultiplied BGRA
 /// bytes, for use as an `AlphaBlend` source in the preview handler.
 pub unsafe fn create_premul_hbitmap(
@@ -1,16 +1,17 @@
 //! Drag-and-drop auto mode (Windows): if invoked only with existing file/folder
 //! paths, auto-route each to decode/encode based on extension and derive outputs
-//! by changing the extension.
+//! by changing the extension. `--dry-run` may be mixed in to print the plan.
 
 use std::ops::ControlFlow;
 use std::path::Path;
 
-use ltk_texture::tex::MipmapFilter;
+use ltk_tex_pipeline::decode::{DecodeOptions, decode_file};
+use ltk_tex_pipeline::encode::{EncodeOptions, encode_file};
 use tracing::info;
 
-use crate::batch::{run_batch, sibling_with_extension};
-use crate::commands::{DecodeCommandOptions, EncodeCommandOptions, decode, encode};
-use crate::utils::{ValidFormat, collect_input_files};
+use crate::batch::{Batch, Job, RunArgs, print_plan, sibling_with_extension};
+use crate::logging::{self, LogArgs};
+use crate::walk::{Walk, collect_input_files};
 
 /// Attempts to handle an invocation whose arguments are all existing file/folder paths
 /// (drag-and-drop style). Returns `Break(result)` if handled, `Continue(())` to proceed
@@ -22,10 +23,15 @@ pub fn try_handle() -> ControlFlow<eyre::Result<()>> {
     }
 
     let mut inputs = Vec::with_capacity(raw.len());
+    let mut dry_run = false;
     for arg in &raw {
-        // If an argument looks like a flag (e.g. --help, -h, /?),
-        // defer to the normal CLI parser so help/version work as expected.
         let lossy = arg.to_string_lossy();
+        if lossy == "--dry-run" {
+            dry_run = true;
+            continue;
+        }
+        // If an argument looks like any other flag (e.g. --help, -h, /?),
+        // defer to the normal CLI parser so help/version work as expected.
         if lossy.starts_with('-') || lossy.starts_with('/') {
             return ControlFlow::Continue(());
         }
@@ -37,63 +43,75 @@ pub fn try_handle() -> ControlFlow<eyre::Result<()>> {
         inputs.push(lossy.into_owned());
     }
 
-    ControlFlow::Break(run_auto_mode(&inputs))
+    if inputs.is_empty() {
+        return ControlFlow::Continue(());
+    }
+
+    ControlFlow::Break(
+        logging::init(&LogArgs::default()).and_then(|()| run_auto_mode(&inputs, dry_run)),
+    )
 }
 
-fn run_auto_mode(inputs: &[String]) -> eyre::Result<()> {
+fn run_auto_mode(inputs: &[String], dry_run: bool) -> eyre::Result<()> {
     // Folders expand to the .tex files they contain (decoded to .png).
-    let files = collect_input_files(inputs, crate::commands::decode::DIR_EXTENSIONS)?;
+    let files = collect_input_files(
+        inputs,
+        crate::commands::decode::DIR_EXTENSIONS,
+        &Walk::default(),
+    )?;
     if files.is_empty() {
         eyre::bail!("no convertible files found");
     }
-    run_batch(&files, auto_convert_file)
+    let jobs = files
+        .into_iter()
+        .map(|input| {
+            let extension = if is_tex(&input) { "png" } else { "tex" };
+            Job {
+                output: sibling_with_extension(&input, extension),
+                input,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if dry_run {
+        print_plan(&jobs);
+        return Ok(());
+    }
+    Batch {
+        command: "auto",
+        format: "auto".to_string(),
+        args: &RunArgs::default(),
+    }
+    .run(&jobs, auto_convert_file)
 }
 
-fn auto_convert_file(input: &Path) -> eyre::Result<()> {
-    let ext = input
-        .extension()
+fn is_tex(path: &Path) -> bool {
+    path.extension()
         .and_then(|s| s.to_str())
-        .map(|s| s.to_lowercase());
-
-    match ext.as_deref() {
-        Some("tex") => {
-            let output = sibling_with_extension(input, "png");
-            info!(
-                input = %input.display(),
-                output = %output,
-                "auto mode: decoding .tex to .png"
-            );
-            decode(DecodeCommandOptions {
-                input: input.to_string_lossy().into_owned(),
-                output,
-                mipmap: 0,
-            })
-        }
-        _ => {
-            let output = sibling_with_extension(input, "tex");
-
-            let format = ValidFormat::Bc3;
-            let weigh_color_by_alpha = false;
-            let generate_mipmaps = true;
-            let mipmap_filter = MipmapFilter::CatmullRom;
-
-            info!(
-                input = %input.display(),
-                output = %output,
-                format = ?format,
-                generate_mipmaps = generate_mipmaps,
-                mipmap_filter = ?mipmap_filter,
-                "auto mode: encoding image to .tex"
-            );
+        .is_some_and(|s| s.eq_ignore_ascii_case("tex"))
+}
 
-            encode(EncodeCommandOptions {
-                input: input.to_string_lossy().into_owned(),
-                output,
-                format,
-                weigh_color_by_alpha,
-                generate_mipmaps,
-                mipmap_filter,
-            })
-        }
+fn auto_convert_file(job: &Job) -> eyre::Result<()> {
+    let input = job.input.as_path();
+    let output = job.output.as_str();
+    if is_tex(input) {
+        info!(
+            input = %input.display(),
+            output = %output,
+            "auto mode: decoding .tex to .png"
+        );
+        decode_file(&input.to_string_lossy(), output, &DecodeOptions::default())?;
+    } else {
+        let options = EncodeOptions::default();
+        info!(
+            input = %input.display(),
+            output = %output,
+            format = ?options.format,
+            generate_mipmaps = options.generate_mipmaps,
+            mipmap_filter = ?options.mipmap_filter,
+            "auto mode: encoding image to .tex"
+        );
+        encode_file(&input.to_string_lossy(), output, &options)?;
     }
+    Ok(())
 }
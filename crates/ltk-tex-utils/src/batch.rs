@@ -1,12 +1,69 @@
 use std::path::{Path, PathBuf};
 
-use crate::utils::collect_input_files;
+use colored::Colorize;
+use ltk_tex_pipeline::batch::{
+    self as pipeline, BatchObserver, BatchOptions, collisions, output_key,
+};
+pub use ltk_tex_pipeline::batch::{
+    BatchError, Job, OverwritePolicy, apply_overwrite_policy, sibling_jobs, sibling_with_extension,
+};
+use ltk_tex_pipeline::io::is_stdio;
+use ltk_tex_pipeline::report::{JobReport, ReportFormat};
+
+use crate::progress::Progress;
+use crate::walk::{Walk, WalkArgs, collect_input_files};
+
+// Options shared by the batch conversion commands.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct BatchArgs {
+    /// Print every input -> output pair that would be converted, flagging
+    /// outputs that already exist or that several inputs map to, without
+    /// writing anything
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// What to do when an output file already exists
+    #[arg(long, value_enum, default_value_t = OverwritePolicy::Always)]
+    pub overwrite: OverwritePolicy,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+// Options controlling how a batch runs and reports, shared by every batch
+// command (including those that write nothing, like `verify`).
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct RunArgs {
+    /// Write a report of every processed file (status, errors, timing, sizes)
+    /// to this path
+    #[arg(long, value_name = "FILE")]
+    pub report: Option<PathBuf>,
+
+    /// Report format; defaults to JUnit XML for `.xml` paths and JSON otherwise
+    #[arg(long, value_enum, requires = "report")]
+    pub report_format: Option<ReportFormat>,
+
+    /// Stop at the first file that fails
+    #[arg(long, conflicts_with = "max_failures")]
+    pub fail_fast: bool,
+
+    /// Stop once more than N files failed
+    #[arg(long, value_name = "N")]
+    pub max_failures: Option<usize>,
+}
+
+/// Process exit code for an error: the [`BatchError`] code, or 1.
+pub fn exit_code(err: &eyre::Report) -> u8 {
+    err.downcast_ref::<BatchError>()
+        .map_or(1, BatchError::exit_code)
+}
 
 /// Merge `-i/--input` with the positional inputs and expand folders into files.
 pub fn gather_inputs(
     flag: Option<String>,
     mut inputs: Vec<String>,
     dir_extensions: &[&str],
+    walk: &WalkArgs,
 ) -> eyre::Result<Vec<PathBuf>> {
     if let Some(flag) = flag {
         inputs.insert(0, flag);
@@ -14,8 +71,11 @@ pub fn gather_inputs(
     if inputs.is_empty() {
         eyre::bail!("missing input; pass -i/--input or provide INPUTS positionally");
     }
+    if inputs.len() > 1 && inputs.iter().any(is_stdio) {
+        eyre::bail!("`-` (stdin) cannot be combined with other inputs");
+    }
 
-    let files = collect_input_files(&inputs, dir_extensions)?;
+    let files = collect_input_files(&inputs, dir_extensions, &Walk::new(walk)?)?;
     if files.is_empty() {
         eyre::bail!(
             "no matching input files found (looked for {} in folders)",
@@ -25,6 +85,23 @@ pub fn gather_inputs(
     Ok(files)
 }
 
+/// Jobs for the commands that rewrite `.tex` files (`transcode`, ...): each
+/// input goes to `output`, to itself with `in_place`, or by default to
+/// `<name>.<suffix>.tex` next to it so the original is kept.
+pub fn rewrite_jobs(
+    files: &[PathBuf],
+    output: Option<&str>,
+    in_place: bool,
+    suffix: &str,
+) -> Vec<Job> {
+    let extension = if in_place {
+        "tex".to_string()
+    } else {
+        format!("{suffix}.tex")
+    };
+    sibling_jobs(files, output, &extension)
+}
+
 /// `-o/--output` is only meaningful when converting a single file.
 pub fn single_output(output: Option<String>, files: &[PathBuf]) -> eyre::Result<Option<String>> {
     if output.is_some() && files.len() > 1 {
@@ -37,26 +114,135 @@ pub fn single_output(output: Option<String>, files: &[PathBuf]) -> eyre::Result<
     Ok(output)
 }
 
-/// Convert each file, logging failures but continuing; errors out at the end if any failed.
-pub fn run_batch(
-    files: &[PathBuf],
-    per_file: impl Fn(&Path) -> eyre::Result<()>,
-) -> eyre::Result<()> {
-    let mut failed = 0usize;
-    for file in files {
-        if let Err(err) = per_file(file) {
-            failed += 1;
-            tracing::error!("failed to convert {}: {err:#}", file.display());
+/// A batch conversion run by one command.
+pub struct Batch<'a> {
+    /// Command name, recorded in reports.
+    pub command: &'a str,
+    /// Format the outputs are written in, recorded in reports.
+    pub format: String,
+    pub args: &'a RunArgs,
+}
+
+impl Batch<'_> {
+    /// Run each job, logging failures and continuing unless `--fail-fast` or
+    /// `--max-failures` says otherwise; errors out with a [`BatchError`] if any
+    /// failed. Writes the `--report`, if any, once done.
+    ///
+    /// Nothing runs if two jobs share an output.
+    pub fn run(
+        &self,
+        jobs: &[Job],
+        per_job: impl Fn(&Job) -> eyre::Result<()>,
+    ) -> eyre::Result<()> {
+        let batch = pipeline::Batch {
+            command: self.command,
+            format: self.format.clone(),
+            options: BatchOptions {
+                report: self.args.report.clone(),
+                report_format: self.args.report_format,
+                max_failures: if self.args.fail_fast {
+                    Some(0)
+                } else {
+                    self.args.max_failures
+                },
+            },
+        };
+        let result = batch.run(jobs, &mut ProgressObserver(None), |job| {
+            per_job(job).map_err(Into::into)
+        });
+        match result {
+            Ok(()) => Ok(()),
+            // Keep the `BatchError` itself as the report so `exit_code` finds it.
+            Err(ltk_tex_pipeline::Error::Batch(err)) => Err(err.into()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Shows a batch's [`Progress`] while it runs.
+struct ProgressObserver(Option<Progress>);
+
+impl BatchObserver for ProgressObserver {
+    fn started(&mut self, total: usize) {
+        self.0 = Some(Progress::start(total));
+    }
+
+    fn job_started(&mut self, job: &Job) {
+        if let Some(progress) = &self.0 {
+            progress.begin(&job.input);
         }
     }
-    if failed > 0 {
-        eyre::bail!("{failed} of {} file(s) failed to convert", files.len());
+
+    fn job_finished(&mut self, _job: &Job, _report: &JobReport) {
+        if let Some(progress) = &mut self.0 {
+            progress.advance();
+        }
+    }
+
+    fn finished(&mut self) {
+        self.0 = None;
+    }
+}
+
+/// Print what a batch would do (`--dry-run`) without touching disk.
+pub fn print_plan(jobs: &[Job]) {
+    let collisions = collisions(jobs);
+    let mut existing = 0usize;
+    for job in jobs {
+        let mut notes = Vec::new();
+        if !is_stdio(&job.output) && Path::new(&job.output).exists() {
+            existing += 1;
+            notes.push("overwrites existing file".yellow().to_string());
+        }
+        if let Some(count) = collisions.get(&output_key(&job.output)) {
+            notes.push(
+                format!("collides with {} other input(s)", count - 1)
+                    .red()
+                    .to_string(),
+            );
+        }
+        let notes = if notes.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", notes.join(", "))
+        };
+        println!(
+            "{} {} -> {}{}",
+            "plan:".bold().blue(),
+            job.input.display(),
+            job.output.bold(),
+            notes
+        );
     }
-    Ok(())
+    println!(
+        "{} {} file(s) would be written, {} overwriting existing files, {} output(s) with colliding inputs",
+        "dry run:".bold().blue(),
+        jobs.len(),
+        existing,
+        collisions.len()
+    );
 }
 
-pub fn sibling_with_extension(input: &Path, extension: &str) -> String {
-    let mut out = input.to_path_buf();
-    out.set_extension(extension);
-    out.to_string_lossy().into_owned()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrite_jobs_keep_the_inputs_unless_in_place() {
+        let files = [PathBuf::from("mod/a.tex"), PathBuf::from("-")];
+        let outputs = |in_place| {
+            rewrite_jobs(&files, None, in_place, "low")
+                .into_iter()
+                .map(|job| job.output)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            outputs(false),
+            [Path::new("mod/a.low.tex").to_string_lossy(), "-".into()]
+        );
+        assert_eq!(
+            outputs(true),
+            [Path::new("mod/a.tex").to_string_lossy(), "-".into()]
+        );
+    }
 }
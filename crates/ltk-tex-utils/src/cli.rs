@@ -1,10 +1,17 @@
+use std::process::ExitCode;
+
 use clap::{
     ColorChoice, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum,
     builder::{Styles, styling::AnsiColor},
 };
 
-use crate::commands::{self, DecodeArgs, EncodeArgs, InfoArgs};
+use crate::batch;
+use crate::commands::{
+    self, DecodeArgs, DedupeArgs, DiffArgs, DownscaleArgs, EditArgs, EncodeArgs, FlipbookArgs,
+    InfoArgs, LintArgs, MontageArgs, TranscodeArgs, VerifyArgs, ViewArgs,
+};
 use crate::handler;
+use crate::logging::LogArgs;
 use crate::shell;
 
 #[derive(Parser, Debug)]
@@ -15,6 +22,9 @@ pub struct Args {
     #[arg(long, global = true, value_enum, default_value_t = PauseMode::Never)]
     pub pause: PauseMode,
 
+    #[command(flatten)]
+    pub log: LogArgs,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -35,6 +45,36 @@ pub enum Commands {
     Info(InfoArgs),
     Encode(EncodeArgs),
     Decode(DecodeArgs),
+    /// Re-encode textures to another format without going through an image
+    /// file, keeping their dimensions and optionally their mip chain
+    Transcode(TranscodeArgs),
+    /// Halve the dimensions of textures, dropping their top mip levels when
+    /// they have mipmaps and resampling them otherwise
+    Downscale(DownscaleArgs),
+    /// Fix up texture headers (mipmap flag, resource type, top mip) without
+    /// re-encoding, checking the result is still consistent
+    Edit(EditArgs),
+    /// Check textures end to end (header, data length, block alignment, every
+    /// mip decodes) and exit non-zero if any is broken
+    Verify(VerifyArgs),
+    /// Check textures against a packaging policy (size limits, mipmaps,
+    /// formats, ...) and suggest how to fix violations
+    Lint(LintArgs),
+    /// Find textures shipped more than once (same image, whatever the file
+    /// name or format) and report the bytes they waste
+    Dedupe(DedupeArgs),
+    /// Compare two folders of textures (e.g. before and after a patch) and
+    /// report added, removed and changed files with how much their pixels moved
+    Diff(DiffArgs),
+    /// Show a texture in the terminal (half blocks, or kitty/iTerm2/sixel
+    /// graphics) with its format, size, mip count and alpha
+    View(ViewArgs),
+    /// Render every texture of a folder or WAD as captioned thumbnails on a
+    /// single PNG contact sheet
+    Montage(MontageArgs),
+    /// Play a flipbook texture (a grid of animation frames) as an animated
+    /// GIF, APNG or WebP
+    Flipbook(FlipbookArgs),
     /// Manage Windows Explorer context-menu integration
     Shell {
         #[command(subcommand)]
@@ -65,34 +105,41 @@ pub fn parse() -> Args {
 
 pub fn run(command: Commands) -> eyre::Result<()> {
     match command {
-        Commands::Info(args) => {
-            commands::info::run(args);
-            Ok(())
-        }
+        Commands::Info(args) => commands::info::run(args),
         Commands::Encode(args) => commands::encode::run(args),
         Commands::Decode(args) => commands::decode::run(args),
+        Commands::Transcode(args) => commands::transcode::run(args),
+        Commands::Downscale(args) => commands::downscale::run(args),
+        Commands::Edit(args) => commands::edit::run(args),
+        Commands::Verify(args) => commands::verify::run(args),
+        Commands::View(args) => commands::view::run(args),
+        Commands::Montage(args) => commands::montage::run(args),
+        Commands::Flipbook(args) => commands::flipbook::run(args),
+        Commands::Lint(args) => commands::lint::run(args),
+        Commands::Dedupe(args) => commands::dedupe::run(args),
+        Commands::Diff(args) => commands::diff::run(args),
         Commands::Shell { action } => shell::run(&action),
         Commands::Handler { action } => handler::run(&action),
     }
 }
 
-/// Apply the `--pause` behavior, printing the error before pausing so it stays
-/// visible in a console window spawned by Explorer.
-pub fn finish(result: eyre::Result<()>, pause: PauseMode) -> eyre::Result<()> {
+/// Print the error (if any) and apply the `--pause` behavior, so the error stays
+/// visible in a console window spawned by Explorer. Batch failures get their own
+/// exit codes (see `batch::BatchError`), other errors exit with 1.
+pub fn finish(result: eyre::Result<()>, pause: PauseMode) -> ExitCode {
     match result {
         Ok(()) => {
             if pause == PauseMode::Always {
                 pause_prompt();
             }
-            Ok(())
+            ExitCode::SUCCESS
         }
         Err(err) => {
+            eprintln!("Error: {err:?}");
             if pause != PauseMode::Never {
-                eprintln!("error: {err:?}");
                 pause_prompt();
-                std::process::exit(1);
             }
-            Err(err)
+            ExitCode::from(batch::exit_code(&err))
         }
     }
 }
@@ -1,23 +1,27 @@
-use std::{
-    fs::{self, File},
-    io::{BufReader, BufWriter},
-    path::Path,
-};
+use std::path::PathBuf;
 
-use image_dds::{ImageFormat, Mipmaps, Quality};
-use ltk_texture::Tex;
+use ltk_tex_pipeline::decode::{DecodeOptions, decode_file, decode_tex, read_tex};
+use ltk_tex_pipeline::format::DecodeOutputFormat;
+use ltk_tex_pipeline::io::is_stdio;
+use ltk_tex_pipeline::layers::LayerMode;
 use tracing::info;
 
-use crate::batch::{gather_inputs, run_batch, sibling_with_extension, single_output};
-use crate::utils::DecodeOutputFormat;
+use crate::batch::{
+    Batch, BatchArgs, Job, apply_overwrite_policy, gather_inputs, print_plan, sibling_jobs,
+    single_output,
+};
+use crate::logging;
+use crate::wad::{self, WadArgs, WadTextures};
+use crate::walk::WalkArgs;
 
 /// File extensions picked up when a directory is passed to `decode`.
 pub const DIR_EXTENSIONS: &[&str] = &["tex"];
 
 #[derive(clap::Args, Debug)]
 pub struct DecodeArgs {
-    /// Textures (.tex) or folders to decode; folders are searched
-    /// recursively for .tex files
+    /// Textures (.tex), folders or WAD archives (.wad.client) to decode; folders
+    /// are searched recursively for .tex files, and every .tex entry of a WAD is
+    /// decoded. `-` reads a texture from stdin
     #[arg(value_name = "INPUTS", required_unless_present = "input")]
     pub inputs: Vec<String>,
 
@@ -29,76 +33,120 @@ pub struct DecodeArgs {
     /// The output directory will be created if it doesn't exist
     /// The output format will be determined by the file extension
     /// Defaults to writing next to each input with the `--format` extension.
+    /// `-` writes to stdout in the `--format` format.
+    /// For a single WAD input, the folder the entries are extracted into
+    /// (defaults to the archive's path without `.wad.client`)
     #[arg(short, long, value_name = "OUTPUT")]
     pub output: Option<String>,
 
-    /// Output image format used when --output is not given
+    /// Output image format used when --output is not given or is `-`
     #[arg(short, long, value_enum, default_value_t = DecodeOutputFormat::Png)]
     pub format: DecodeOutputFormat,
 
     /// Mipmap to decode (0 = largest)
     #[arg(short, long, default_value = "0")]
     pub mipmap: u32,
-}
 
-pub fn run(args: DecodeArgs) -> eyre::Result<()> {
-    let files = gather_inputs(args.input, args.inputs, DIR_EXTENSIONS)?;
-    let output = single_output(args.output, &files)?;
-    run_batch(&files, |file| {
-        let out = output
-            .clone()
-            .unwrap_or_else(|| sibling_with_extension(file, args.format.extension()));
-        info!("decoding {} -> {}", file.display(), out);
-        decode(DecodeCommandOptions {
-            input: file.to_string_lossy().into_owned(),
-            output: out,
-            mipmap: args.mipmap,
-        })
-    })
-}
+    /// How to export the faces of cubemaps and the slices of volume textures
+    /// (2D textures are unaffected)
+    #[arg(long, value_enum, default_value_t = LayerMode::First)]
+    pub layers: LayerMode,
 
-pub struct DecodeCommandOptions {
-    pub input: String,
-    pub output: String,
-    pub mipmap: u32,
-}
+    #[command(flatten)]
+    pub wad: WadArgs,
+
+    #[command(flatten)]
+    pub walk: WalkArgs,
 
-pub fn decode(options: DecodeCommandOptions) -> eyre::Result<()> {
-    let file = File::open(&options.input)?;
-    let mut reader = BufReader::new(file);
+    #[command(flatten)]
+    pub batch: BatchArgs,
+}
 
-    let tex = Tex::from_reader(&mut reader)?;
+pub fn run(args: DecodeArgs) -> eyre::Result<()> {
+    let files = gather_inputs(args.input, args.inputs, DIR_EXTENSIONS, &args.walk)?;
+    let (wad_files, files): (Vec<PathBuf>, Vec<PathBuf>) =
+        files.into_iter().partition(|file| wad::is_wad(file));
+    let wads = open_wads(&wad_files, &files, args.output.as_deref(), &args.wad)?;
 
-    let image = tex.decode_mipmap(options.mipmap)?;
-    let image = image.into_rgba_image()?;
+    let output = if wads.is_empty() {
+        single_output(args.output, &files)?
+    } else {
+        None
+    };
+    let mut jobs = sibling_jobs(&files, output.as_deref(), args.format.extension());
+    for (wad, out_dir) in &wads {
+        for entry in wad.virtual_paths() {
+            let mut out = out_dir.join(wad.entry_name(&entry)?);
+            out.set_extension(args.format.extension());
+            jobs.push(Job {
+                input: entry,
+                output: out.to_string_lossy().into_owned(),
+            });
+        }
+    }
+    if jobs.is_empty() {
+        eyre::bail!("no .tex entries matched in {} WAD archive(s)", wads.len());
+    }
 
-    let output_path = Path::new(&options.output);
-    if let Some(parent) = output_path.parent()
-        && !parent.as_os_str().is_empty()
-    {
-        fs::create_dir_all(parent)?;
+    let jobs = apply_overwrite_policy(jobs, args.batch.overwrite);
+    if args.batch.dry_run {
+        print_plan(&jobs);
+        return Ok(());
+    }
+    if jobs.iter().any(|job| is_stdio(&job.output)) {
+        logging::reserve_stdout();
     }
+    let batch = Batch {
+        command: "decode",
+        format: args.format.extension().to_string(),
+        args: &args.batch.run,
+    };
+    batch.run(&jobs, |job| {
+        info!("decoding {} -> {}", job.input.display(), job.output);
+        let options = DecodeOptions {
+            format: args.format,
+            mipmap: args.mipmap,
+            layers: args.layers,
+        };
+        match wads.iter().find(|(wad, _)| wad.contains(&job.input)) {
+            Some((wad, _)) => {
+                let tex = read_tex(&wad.read(&job.input)?)?;
+                Ok(decode_tex(&tex, &job.output, &options)?)
+            }
+            None => Ok(decode_file(
+                &job.input.to_string_lossy(),
+                &job.output,
+                &options,
+            )?),
+        }
+    })
+}
 
-    let is_dds = output_path
-        .extension()
-        .and_then(|e| e.to_str())
-        .is_some_and(|e| e.eq_ignore_ascii_case("dds"));
-
-    if is_dds {
-        // The `image` crate cannot write DDS; emit an uncompressed RGBA8 DDS
-        // holding the single decoded mip.
-        let dds = image_dds::dds_from_image(
-            &image,
-            ImageFormat::Rgba8Unorm,
-            Quality::Fast,
-            Mipmaps::Disabled,
-        )?;
-        let file = File::create(output_path)?;
-        let mut writer = BufWriter::new(file);
-        dds.write(&mut writer)?;
-    } else {
-        image.save(&options.output)?;
+/// Mount the WAD inputs, pairing each with the folder its entries are decoded
+/// into (`-o`, or the archive's path without `.wad.client`).
+fn open_wads(
+    wad_files: &[PathBuf],
+    loose: &[PathBuf],
+    output: Option<&str>,
+    wad_args: &WadArgs,
+) -> eyre::Result<Vec<(WadTextures, PathBuf)>> {
+    if wad_files.is_empty() {
+        return Ok(Vec::new());
+    }
+    if output.is_some() && (wad_files.len() > 1 || !loose.is_empty()) {
+        eyre::bail!("-o/--output names the extraction folder and needs a single WAD input");
+    }
+    if output.is_some_and(is_stdio) {
+        eyre::bail!("WAD entries are written to a folder and cannot target stdout");
     }
 
-    Ok(())
+    wad_files
+        .iter()
+        .map(|path| {
+            let wad = WadTextures::open(path, wad_args)
+                .map_err(|e| eyre::eyre!("failed to open {}: {e:#}", path.display()))?;
+            let out_dir = output.map_or_else(|| wad::default_output_dir(path), PathBuf::from);
+            Ok((wad, out_dir))
+        })
+        .collect()
 }
@@ -0,0 +1,296 @@
+//! `dedupe`: find textures shipped several times in a mod tree. Textures are
+//! grouped by their decoded top mip, so the same image stored in different
+//! formats (or with and without mipmaps) is caught as well as identical files;
+//! `--near` additionally groups images that only look alike.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use image::{RgbaImage, imageops};
+use indicatif::HumanBytes;
+use ltk_tex_pipeline::format::format_name;
+use ltk_tex_pipeline::layers;
+use ltk_texture::Tex;
+use tracing::info;
+use xxhash_rust::xxh3::xxh3_128;
+
+use crate::batch::{Batch, Job, RunArgs, gather_inputs};
+use crate::wad::{TextureSources, WadArgs};
+use crate::walk::WalkArgs;
+
+#[derive(clap::Args, Debug)]
+pub struct DedupeArgs {
+    /// Textures (.tex), folders or WAD archives (.wad.client) to search for
+    /// duplicates; folders are searched recursively for .tex files
+    #[arg(value_name = "INPUTS", required_unless_present = "input")]
+    pub inputs: Vec<String>,
+
+    /// Texture (.tex) or folder to search (alternative to positional INPUTS)
+    #[arg(short, long, value_name = "INPUT")]
+    pub input: Option<String>,
+
+    /// Also report near-duplicates: images whose perceptual hashes differ in
+    /// at most `--max-distance` bits (resized, re-compressed or slightly
+    /// retouched copies)
+    #[arg(long)]
+    pub near: bool,
+
+    /// Largest perceptual hash distance (out of 64 bits) for `--near`
+    #[arg(long, value_name = "BITS", default_value_t = 4, requires = "near",
+          value_parser = clap::value_parser!(u32).range(0..=64))]
+    pub max_distance: u32,
+
+    #[command(flatten)]
+    pub wad: WadArgs,
+
+    #[command(flatten)]
+    pub walk: WalkArgs,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+/// What `dedupe` knows about one texture.
+#[derive(Debug, Clone)]
+pub struct Fingerprint {
+    pub path: PathBuf,
+    pub size: u64,
+    pub format: &'static str,
+    /// Hash of the whole file.
+    pub bytes: u128,
+    /// Hash of the decoded top mip (every face/slice) and its dimensions.
+    pub pixels: u128,
+    /// Difference hash of the top mip, for `--near`.
+    pub perceptual: u64,
+}
+
+impl Fingerprint {
+    pub fn of(path: PathBuf, data: &[u8]) -> eyre::Result<Self> {
+        let tex = Tex::from_reader(&mut &data[..])?;
+        let images = layers::decode_level(&tex, 0)?;
+        let mut pixels = Vec::with_capacity(8 + images.len() * images[0].as_raw().len());
+        pixels.extend((tex.width as u32).to_le_bytes());
+        pixels.extend((tex.height as u32).to_le_bytes());
+        for image in &images {
+            pixels.extend_from_slice(image.as_raw());
+        }
+        Ok(Self {
+            path,
+            size: data.len() as u64,
+            format: format_name(tex.format),
+            bytes: xxh3_128(data),
+            pixels: xxh3_128(&pixels),
+            perceptual: difference_hash(&images[0]),
+        })
+    }
+}
+
+pub fn run(args: DedupeArgs) -> eyre::Result<()> {
+    let files = gather_inputs(
+        args.input,
+        args.inputs,
+        crate::commands::decode::DIR_EXTENSIONS,
+        &args.walk,
+    )?;
+    let (sources, textures) = TextureSources::open(files, &args.wad)?;
+    let jobs = textures
+        .into_iter()
+        .map(|input| Job {
+            input,
+            output: String::new(),
+        })
+        .collect::<Vec<_>>();
+    if jobs.is_empty() {
+        eyre::bail!("no textures to compare");
+    }
+
+    let fingerprints = RefCell::new(Vec::with_capacity(jobs.len()));
+    let batch = Batch {
+        command: "dedupe",
+        format: "tex".to_string(),
+        args: &args.run,
+    };
+    // Textures that fail to decode are reported by the batch and left out.
+    let result = batch.run(&jobs, |job| {
+        let data = sources.read(&job.input)?;
+        let fingerprint = Fingerprint::of(job.input.clone(), &data)?;
+        fingerprints.borrow_mut().push(fingerprint);
+        Ok(())
+    });
+    let fingerprints = fingerprints.into_inner();
+
+    let groups = duplicate_groups(&fingerprints);
+    let wasted: u64 = groups.iter().map(|group| wasted_bytes(group)).sum();
+    for group in &groups {
+        println!(
+            "{} copies of the same image, {} wasted:",
+            group.len(),
+            HumanBytes(wasted_bytes(group))
+        );
+        print_group(group);
+    }
+    if args.near {
+        for group in near_groups(&fingerprints, args.max_distance) {
+            println!("{} similar images:", group.len());
+            print_group(&group);
+        }
+    }
+    info!(
+        "{} texture(s) compared, {} duplicate group(s), {} wasted",
+        fingerprints.len(),
+        groups.len(),
+        HumanBytes(wasted)
+    );
+    result
+}
+
+fn print_group(group: &[&Fingerprint]) {
+    for (i, texture) in group.iter().enumerate() {
+        let same_file = group[..i]
+            .iter()
+            .find(|earlier| earlier.bytes == texture.bytes);
+        let note = match same_file {
+            Some(earlier) => format!(", same file as {}", earlier.path.display()),
+            None => String::new(),
+        };
+        println!(
+            "  {} ({}, {}{note})",
+            texture.path.display(),
+            texture.format,
+            HumanBytes(texture.size)
+        );
+    }
+}
+
+/// Textures with the same decoded pixels, largest waste first. Members keep
+/// the order of `fingerprints`.
+pub fn duplicate_groups(fingerprints: &[Fingerprint]) -> Vec<Vec<&Fingerprint>> {
+    let mut by_pixels: HashMap<u128, Vec<&Fingerprint>> = HashMap::new();
+    for fingerprint in fingerprints {
+        by_pixels
+            .entry(fingerprint.pixels)
+            .or_default()
+            .push(fingerprint);
+    }
+    let mut groups = by_pixels
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect::<Vec<_>>();
+    groups.sort_by(|a, b| {
+        wasted_bytes(b)
+            .cmp(&wasted_bytes(a))
+            .then_with(|| a[0].path.cmp(&b[0].path))
+    });
+    groups
+}
+
+/// Bytes saved by keeping only the smallest texture of `group`.
+pub fn wasted_bytes(group: &[&Fingerprint]) -> u64 {
+    let total: u64 = group.iter().map(|texture| texture.size).sum();
+    total - group.iter().map(|texture| texture.size).min().unwrap_or(0)
+}
+
+/// Clusters of images within `max_distance` bits of each other (transitively)
+/// that are not all exact duplicates of one another.
+pub fn near_groups(fingerprints: &[Fingerprint], max_distance: u32) -> Vec<Vec<&Fingerprint>> {
+    // Union-find over the textures; exact duplicates are at distance 0.
+    let mut parent = (0..fingerprints.len()).collect::<Vec<_>>();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for (i, a) in fingerprints.iter().enumerate() {
+        for (j, b) in fingerprints.iter().enumerate().skip(i + 1) {
+            if (a.perceptual ^ b.perceptual).count_ones() <= max_distance {
+                let (ri, rj) = (root(&mut parent, i), root(&mut parent, j));
+                parent[rj] = ri;
+            }
+        }
+    }
+
+    let mut clusters: Vec<Vec<&Fingerprint>> = Vec::new();
+    let mut index = HashMap::new();
+    for (i, fingerprint) in fingerprints.iter().enumerate() {
+        let root = root(&mut parent, i);
+        let slot = *index.entry(root).or_insert_with(|| {
+            clusters.push(Vec::new());
+            clusters.len() - 1
+        });
+        clusters[slot].push(fingerprint);
+    }
+    clusters
+        .into_iter()
+        .filter(|cluster| cluster.iter().any(|t| t.pixels != cluster[0].pixels))
+        .collect()
+}
+
+/// 64-bit difference hash: the image shrunk to 9x8 luma (weighted by alpha),
+/// one bit per horizontally adjacent pair telling whether it gets brighter.
+pub fn difference_hash(image: &RgbaImage) -> u64 {
+    let small = imageops::resize(image, 9, 8, imageops::FilterType::Triangle);
+    let luma = |x, y| {
+        let [r, g, b, a] = small.get_pixel(x, y).0.map(u32::from);
+        (r * 299 + g * 587 + b * 114) * a / 255
+    };
+    let mut hash = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash = (hash << 1) | u64::from(luma(x, y) < luma(x + 1, y));
+        }
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+    use ltk_texture::tex::{EncodeFormat, EncodeOptions};
+
+    use super::*;
+
+    fn encode(image: &RgbaImage, format: EncodeFormat) -> Vec<u8> {
+        let tex = Tex::encode_rgba_image(image, EncodeOptions::new(format)).unwrap();
+        let mut data = Vec::new();
+        tex.write(&mut data).unwrap();
+        data
+    }
+
+    fn gradient(shift: u8) -> RgbaImage {
+        RgbaImage::from_fn(16, 16, |x, y| {
+            Rgba([(x * 16) as u8, (y * 16) as u8 + shift, 0, 255])
+        })
+    }
+
+    #[test]
+    fn groups_identical_images_across_formats() {
+        let image = gradient(0);
+        let bgra = encode(&image, EncodeFormat::Bgra8);
+        let other = encode(&gradient(8), EncodeFormat::Bgra8);
+        let fingerprints = [
+            Fingerprint::of("a.tex".into(), &bgra).unwrap(),
+            Fingerprint::of("b.tex".into(), &bgra).unwrap(),
+            Fingerprint::of("c.tex".into(), &other).unwrap(),
+            Fingerprint::of("d.tex".into(), &encode(&image, EncodeFormat::Rgba16Float)).unwrap(),
+        ];
+
+        let groups = duplicate_groups(&fingerprints);
+        assert_eq!(groups.len(), 1);
+        let paths = groups[0].iter().map(|t| t.path.to_str().unwrap());
+        assert_eq!(paths.collect::<Vec<_>>(), ["a.tex", "b.tex", "d.tex"]);
+        // Keeping the BGRA8 copy saves the other BGRA8 file and the RGBA16F one.
+        assert_eq!(
+            wasted_bytes(&groups[0]),
+            fingerprints[0].size + fingerprints[3].size
+        );
+
+        // The brighter copy only shows up as a near-duplicate.
+        let near = near_groups(&fingerprints, 4);
+        assert_eq!(near.len(), 1);
+        assert_eq!(near[0].len(), 4);
+        assert!(near_groups(&fingerprints[..2], 4).is_empty());
+    }
+}
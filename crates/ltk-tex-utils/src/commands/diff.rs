@@ -0,0 +1,307 @@
+//! `diff`: compare two trees of `.tex` files (e.g. the game assets before and
+//! after a patch), matching them by relative path, and report what was added,
+//! removed or changed along with how much the pixels moved.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+
+use image::{ImageFormat, Rgba, RgbaImage, imageops};
+use ltk_tex_handler_shared::scale_image;
+use ltk_tex_pipeline::format::format_name;
+use ltk_tex_pipeline::io::create_output;
+use ltk_tex_pipeline::layers;
+use ltk_texture::Tex;
+use tracing::info;
+
+use crate::batch::{Batch, Job, RunArgs};
+use crate::commands::montage::{self, Tile};
+use crate::walk::{Walk, WalkArgs, collect_input_files};
+
+/// Largest side of each image in a comparison PNG.
+const COMPARISON_SIZE: u32 = 512;
+
+#[derive(clap::Args, Debug)]
+pub struct DiffArgs {
+    /// Folder with the old textures
+    #[arg(value_name = "OLD")]
+    pub old: PathBuf,
+
+    /// Folder with the new textures
+    #[arg(value_name = "NEW")]
+    pub new: PathBuf,
+
+    /// Write an old | new | difference PNG for each changed texture into this
+    /// folder, at the texture's relative path
+    #[arg(long, value_name = "DIR")]
+    pub images: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub walk: WalkArgs,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+/// How the pixels of two versions of a texture differ.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelDiff {
+    /// Fraction of pixels with any channel changed.
+    pub changed: f64,
+    /// Mean absolute difference per channel (0-255).
+    pub mean: f64,
+    /// Largest difference of any channel.
+    pub max: u8,
+    /// Peak signal-to-noise ratio in dB; infinite for identical images.
+    pub psnr: f64,
+}
+
+pub fn run(args: DiffArgs) -> eyre::Result<()> {
+    let walk = Walk::new(&args.walk)?;
+    let old = textures(&args.old, &walk)?;
+    let new = textures(&args.new, &walk)?;
+
+    let mut lines = BTreeMap::new();
+    for path in old.keys().filter(|path| !new.contains_key(*path)) {
+        lines.insert(path.clone(), ("removed", String::new()));
+    }
+    for path in new.keys().filter(|path| !old.contains_key(*path)) {
+        lines.insert(path.clone(), ("added", String::new()));
+    }
+    let jobs = new
+        .iter()
+        .filter(|(path, _)| old.contains_key(*path))
+        .map(|(path, file)| Job {
+            input: file.clone(),
+            output: args.images.as_ref().map_or_else(String::new, |dir| {
+                dir.join(path)
+                    .with_extension("png")
+                    .to_string_lossy()
+                    .into_owned()
+            }),
+        })
+        .collect::<Vec<_>>();
+
+    let changes = RefCell::new(Vec::new());
+    let batch = Batch {
+        command: "diff",
+        format: "png".to_string(),
+        args: &args.run,
+    };
+    let result = batch.run(&jobs, |job| {
+        let relative = job.input.strip_prefix(&args.new)?;
+        let old_bytes = std::fs::read(&old[relative])?;
+        let new_bytes = std::fs::read(&job.input)?;
+        if old_bytes == new_bytes {
+            return Ok(());
+        }
+        let old_tex = Tex::from_reader(&mut old_bytes.as_slice())?;
+        let new_tex = Tex::from_reader(&mut new_bytes.as_slice())?;
+        let (old_image, new_image) = comparable_images(&old_tex, &new_tex)?;
+        let pixels = compare(&old_image, &new_image);
+
+        let mut details = describe(&old_tex, &new_tex, &pixels);
+        if !job.output.is_empty() && pixels.max > 0 {
+            write_comparison(&job.output, &old_tex, &new_tex, &old_image, &new_image)?;
+            details += &format!(" ({})", job.output);
+        }
+        changes
+            .borrow_mut()
+            .push((relative.to_path_buf(), ("changed", details)));
+        Ok(())
+    });
+    let changed = changes.into_inner();
+    let changed_count = changed.len();
+    lines.extend(changed);
+
+    for (path, (kind, details)) in &lines {
+        if details.is_empty() {
+            println!("{kind:<8} {}", path.display());
+        } else {
+            println!("{kind:<8} {}: {details}", path.display());
+        }
+    }
+    let count = |wanted: &str| lines.values().filter(|(kind, _)| *kind == wanted).count();
+    let (added, removed) = (count("added"), count("removed"));
+    info!(
+        "{added} added, {removed} removed, {changed_count} changed, {} unchanged",
+        jobs.len() - changed_count
+    );
+    result
+}
+
+/// The `.tex` files under `root`, keyed by their path relative to it.
+fn textures(root: &Path, walk: &Walk) -> eyre::Result<BTreeMap<PathBuf, PathBuf>> {
+    if !root.is_dir() {
+        eyre::bail!("{} is not a folder", root.display());
+    }
+    let files = collect_input_files(
+        &[root.to_string_lossy().into_owned()],
+        crate::commands::decode::DIR_EXTENSIONS,
+        walk,
+    )?;
+    Ok(files
+        .into_iter()
+        .filter_map(|file| Some((file.strip_prefix(root).ok()?.to_path_buf(), file)))
+        .collect())
+}
+
+/// The top mips of both textures at the same size: every face/slice stacked
+/// vertically (only the first if the layer counts differ), with the new image
+/// resampled to the old dimensions if those changed.
+pub fn comparable_images(old: &Tex, new: &Tex) -> eyre::Result<(RgbaImage, RgbaImage)> {
+    let mut old_layers = layers::decode_level(old, 0)?;
+    let mut new_layers = layers::decode_level(new, 0)?;
+    if old_layers.len() != new_layers.len() {
+        old_layers.truncate(1);
+        new_layers.truncate(1);
+    }
+    let (old, mut new) = (layers::stack(&old_layers), layers::stack(&new_layers));
+    if new.dimensions() != old.dimensions() {
+        new = imageops::resize(
+            &new,
+            old.width(),
+            old.height(),
+            imageops::FilterType::Triangle,
+        );
+    }
+    Ok((old, new))
+}
+
+/// Pixel difference metrics of two images of the same size.
+pub fn compare(old: &RgbaImage, new: &RgbaImage) -> PixelDiff {
+    let (mut changed, mut sum, mut squares, mut max) = (0u64, 0u64, 0u64, 0u8);
+    for (a, b) in old.pixels().zip(new.pixels()) {
+        let mut pixel_changed = false;
+        for (a, b) in a.0.iter().zip(b.0) {
+            let d = a.abs_diff(b);
+            pixel_changed |= d > 0;
+            sum += d as u64;
+            squares += d as u64 * d as u64;
+            max = max.max(d);
+        }
+        changed += pixel_changed as u64;
+    }
+    let pixels = (old.width() as u64 * old.height() as u64).max(1) as f64;
+    let mse = squares as f64 / (pixels * 4.0);
+    PixelDiff {
+        changed: changed as f64 / pixels,
+        mean: sum as f64 / (pixels * 4.0),
+        max,
+        psnr: 10.0 * (255.0 * 255.0 / mse).log10(),
+    }
+}
+
+/// The header fields that changed, then the pixel metrics.
+fn describe(old: &Tex, new: &Tex, pixels: &PixelDiff) -> String {
+    let mut parts = Vec::new();
+    if old.format != new.format {
+        parts.push(format!(
+            "{} -> {}",
+            format_name(old.format),
+            format_name(new.format)
+        ));
+    }
+    if (old.width, old.height) != (new.width, new.height) {
+        parts.push(format!(
+            "{}x{} -> {}x{}",
+            old.width, old.height, new.width, new.height
+        ));
+    }
+    if old.mip_count != new.mip_count {
+        parts.push(format!("mips {} -> {}", old.mip_count, new.mip_count));
+    }
+    if old.resource_type != new.resource_type {
+        parts.push(format!(
+            "{:?} -> {:?}",
+            old.resource_type, new.resource_type
+        ));
+    }
+    if pixels.max == 0 {
+        parts.push("pixels identical".to_string());
+    } else {
+        parts.push(format!(
+            "{:.1}% of pixels differ, mean {:.2}, max {}, PSNR {:.1} dB",
+            pixels.changed * 100.0,
+            pixels.mean,
+            pixels.max,
+            pixels.psnr
+        ));
+    }
+    parts.join(", ")
+}
+
+/// Write old, new and a heat map of their differences side by side.
+fn write_comparison(
+    output: &str,
+    old_tex: &Tex,
+    new_tex: &Tex,
+    old: &RgbaImage,
+    new: &RgbaImage,
+) -> eyre::Result<()> {
+    let heat = RgbaImage::from_fn(old.width(), old.height(), |x, y| {
+        let (a, b) = (old.get_pixel(x, y).0, new.get_pixel(x, y).0);
+        let d = (0..4).map(|c| a[c].abs_diff(b[c])).max().unwrap_or(0);
+        let d = d.saturating_mul(4);
+        Rgba([d, d / 4, 0, 255])
+    });
+    let size = old.width().max(old.height()).clamp(64, COMPARISON_SIZE);
+    let tile = |image: &RgbaImage, name: String, details: String| {
+        let (pixels, width, height) =
+            scale_image(image.as_raw(), image.width(), image.height(), size);
+        Tile {
+            image: RgbaImage::from_raw(width, height, pixels).expect("scale_image size"),
+            name,
+            details,
+        }
+    };
+    let caption = |tex: &Tex| format!("{} {}x{}", format_name(tex.format), tex.width, tex.height);
+    let tiles = [
+        tile(old, "old".to_string(), caption(old_tex)),
+        tile(new, "new".to_string(), caption(new_tex)),
+        tile(&heat, "difference".to_string(), "x4".to_string()),
+    ];
+
+    let mut png = Vec::new();
+    montage::compose(&tiles, size, 3).write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    let mut writer = create_output(output)?;
+    writer.write_all(&png)?;
+    Ok(writer.commit()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use ltk_texture::tex::{EncodeFormat, EncodeOptions};
+
+    use super::*;
+
+    #[test]
+    fn measures_pixel_differences() {
+        let old = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+        assert_eq!(compare(&old, &old).max, 0);
+        assert!(compare(&old, &old).psnr.is_infinite());
+
+        let mut new = old.clone();
+        new.put_pixel(0, 0, Rgba([110, 100, 100, 255]));
+        let diff = compare(&old, &new);
+        assert_eq!(diff.changed, 1.0 / 16.0);
+        assert_eq!(diff.max, 10);
+        assert_eq!(diff.mean, 10.0 / 64.0);
+        assert!((diff.psnr - 10.0 * (255.0f64 * 255.0 / (100.0 / 64.0)).log10()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn compares_textures_of_different_sizes() {
+        let image = RgbaImage::from_pixel(8, 8, Rgba([0, 0, 255, 255]));
+        let old = Tex::encode_rgba_image(&image, EncodeOptions::new(EncodeFormat::Bgra8)).unwrap();
+        let small = imageops::resize(&image, 4, 4, imageops::FilterType::Triangle);
+        let new = Tex::encode_rgba_image(&small, EncodeOptions::new(EncodeFormat::Bgra8)).unwrap();
+
+        let (a, b) = comparable_images(&old, &new).unwrap();
+        assert_eq!(b.dimensions(), (8, 8));
+        let diff = compare(&a, &b);
+        assert_eq!(diff.max, 0);
+        assert_eq!(describe(&old, &new, &diff), "8x8 -> 4x4, pixels identical");
+    }
+}
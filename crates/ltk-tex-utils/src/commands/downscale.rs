@@ -0,0 +1,204 @@
+//! `downscale`: shrink `.tex` files by powers of two. Textures with mipmaps
+//! just lose their top levels (no recompression); others are resampled and
+//! re-encoded in their own format.
+
+use ltk_tex_pipeline::format::{ValidFormat, parse_mipmap_filter};
+use ltk_tex_pipeline::io::{create_output, is_stdio, read_input};
+use ltk_tex_pipeline::layers;
+use ltk_tex_pipeline::texdata::{self, Header};
+use ltk_texture::Tex;
+use ltk_texture::tex::{EncodeOptions, MipmapFilter, ResourceType, encode_rgba};
+use tracing::info;
+
+use crate::batch::{
+    Batch, BatchArgs, apply_overwrite_policy, gather_inputs, print_plan, rewrite_jobs,
+    single_output,
+};
+use crate::logging;
+use crate::walk::WalkArgs;
+
+#[derive(clap::Args, Debug)]
+pub struct DownscaleArgs {
+    /// Textures (.tex) or folders to downscale; folders are searched
+    /// recursively for .tex files. `-` reads a texture from stdin
+    #[arg(value_name = "INPUTS", required_unless_present = "input")]
+    pub inputs: Vec<String>,
+
+    /// Texture (.tex) to downscale (alternative to positional INPUTS)
+    #[arg(short, long, value_name = "INPUT")]
+    pub input: Option<String>,
+
+    /// Output file path (only valid with a single input file).
+    /// Defaults to `<name>.low.tex` next to each input (see `--in-place`).
+    /// `-` writes to stdout
+    #[arg(short, long, value_name = "OUTPUT")]
+    pub output: Option<String>,
+
+    /// Replace each input with its downscaled version
+    #[arg(long, conflicts_with_all = ["output", "overwrite"])]
+    pub in_place: bool,
+
+    /// Number of times to halve the dimensions
+    #[arg(short = 'n', long, default_value_t = 1, conflicts_with = "max_size")]
+    pub levels: u32,
+
+    /// Halve each texture until its width and height are at most this many
+    /// pixels (textures already small enough are written unchanged)
+    #[arg(long, value_name = "PX", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_size: Option<u32>,
+
+    /// Filter used to resample textures without mipmaps
+    #[arg(long, default_value = "catmullrom", value_parser = parse_mipmap_filter)]
+    pub filter: MipmapFilter,
+
+    #[command(flatten)]
+    pub walk: WalkArgs,
+
+    #[command(flatten)]
+    pub batch: BatchArgs,
+}
+
+pub fn run(args: DownscaleArgs) -> eyre::Result<()> {
+    let files = gather_inputs(
+        args.input.clone(),
+        args.inputs.clone(),
+        crate::commands::decode::DIR_EXTENSIONS,
+        &args.walk,
+    )?;
+    let output = single_output(args.output.clone(), &files)?;
+    let jobs = apply_overwrite_policy(
+        rewrite_jobs(&files, output.as_deref(), args.in_place, "low"),
+        args.batch.overwrite,
+    );
+    if args.batch.dry_run {
+        print_plan(&jobs);
+        return Ok(());
+    }
+    if jobs.iter().any(|job| is_stdio(&job.output)) {
+        logging::reserve_stdout();
+    }
+
+    let batch = Batch {
+        command: "downscale",
+        format: "tex".to_string(),
+        args: &args.batch.run,
+    };
+    batch.run(&jobs, |job| {
+        let bytes = read_input(&job.input.to_string_lossy())?;
+        let tex = Tex::from_reader(&mut bytes.as_slice())?;
+        let levels = match args.max_size {
+            Some(max) => levels_to_fit(&tex, max),
+            None => args.levels,
+        };
+        let small = downscale(&tex, levels, args.filter)?;
+        info!(
+            "downscaled {} -> {} ({}x{} -> {}x{})",
+            job.input.display(),
+            job.output,
+            tex.width,
+            tex.height,
+            small.width,
+            small.height
+        );
+
+        let mut writer = create_output(&job.output)?;
+        small.write(&mut writer)?;
+        Ok(writer.commit()?)
+    })
+}
+
+/// How many times `tex` must be halved for both dimensions to fit in `max`.
+pub fn levels_to_fit(tex: &Tex, max: u32) -> u32 {
+    let size = tex.width.max(tex.height) as u32;
+    (0..u16::BITS).find(|n| size >> n <= max).unwrap_or(0)
+}
+
+/// Halve the dimensions of `tex` `levels` times.
+pub fn downscale(tex: &Tex, levels: u32, filter: MipmapFilter) -> eyre::Result<Tex> {
+    if levels == 0 {
+        return Ok(texdata::assemble(&Header::of(tex), &texdata::payload(tex))?);
+    }
+    let source = Header::of(tex);
+    if source.has_mipmaps() {
+        Ok(texdata::drop_top_mips(
+            &source,
+            &texdata::payload(tex),
+            levels,
+        )?)
+    } else {
+        resample(tex, levels, filter)
+    }
+}
+
+/// Resample the single level of a mip-less texture and re-encode it.
+fn resample(tex: &Tex, levels: u32, filter: MipmapFilter) -> eyre::Result<Tex> {
+    if tex.resource_type == ResourceType::VolumeTexture {
+        eyre::bail!("volume textures without mipmaps cannot be downscaled");
+    }
+    let format = ValidFormat::of(tex.format)
+        .ok_or_else(|| eyre::eyre!("{:?} textures cannot be re-encoded", tex.format))?;
+    let options = EncodeOptions {
+        format: format.to_encode_format(false),
+        generate_mipmaps: false,
+        mipmap_filter: filter,
+    };
+
+    let source = Header::of(tex);
+    let (width, height) = source.mip_dimensions(levels);
+    let images = layers::decode_level(tex, 0)?;
+    let mut data = Vec::new();
+    for image in &images {
+        let small = layers::mip_chain(image, levels + 1, filter)
+            .pop()
+            .expect("chain holds levels + 1 images");
+        data.extend(encode_rgba(width, height, small.as_raw(), &options)?);
+    }
+    let header = Header {
+        width: width as u16,
+        height: height as u16,
+        ..source
+    };
+    Ok(texdata::assemble(&header, &data)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::RgbaImage;
+    use ltk_texture::tex::EncodeFormat;
+
+    use super::*;
+
+    fn bgra8(width: u32, height: u32, mipmaps: bool) -> Tex {
+        let image = RgbaImage::from_fn(width, height, |x, y| {
+            image::Rgba([x as u8, y as u8, 0, 255])
+        });
+        let mut options = EncodeOptions::new(EncodeFormat::Bgra8);
+        if mipmaps {
+            options = options.with_mipmaps();
+        }
+        Tex::encode_rgba_image(&image, options).unwrap()
+    }
+
+    #[test]
+    fn dropping_mips_keeps_the_lower_levels_bit_for_bit() {
+        let tex = bgra8(16, 8, true);
+        let small = downscale(&tex, 2, MipmapFilter::Triangle).unwrap();
+        assert_eq!((small.width, small.height), (4, 2));
+        assert_eq!(small.mip_count, tex.mip_count - 2);
+        assert_eq!(
+            small.decode_mipmap(0).unwrap().into_rgba_image().unwrap(),
+            tex.decode_mipmap(2).unwrap().into_rgba_image().unwrap()
+        );
+        assert!(downscale(&tex, 5, MipmapFilter::Triangle).is_err());
+    }
+
+    #[test]
+    fn resamples_textures_without_mips() {
+        let tex = bgra8(16, 8, false);
+        let small = downscale(&tex, 1, MipmapFilter::Triangle).unwrap();
+        assert_eq!((small.width, small.height), (8, 4));
+        assert_eq!(small.mip_count, 1);
+        assert_eq!(levels_to_fit(&tex, 4), 2);
+        assert_eq!(levels_to_fit(&tex, 32), 0);
+    }
+}
@@ -0,0 +1,257 @@
+//! `edit`: fix up TEX headers (mipmap flag, resource type, top mip)
+//! without re-encoding, keeping or cutting the payload to match.
+//!
+//! The mip count of a TEX is implied by its dimensions, so a mip chain can only
+//! be cut at the top (`--top-mip`), never at the bottom.
+
+use ltk_tex_pipeline::io::{create_output, is_stdio, read_input};
+use ltk_tex_pipeline::texdata::{self, Header};
+use ltk_texture::Tex;
+use ltk_texture::tex::{ResourceType, TextureFlags};
+use tracing::info;
+
+use crate::batch::{
+    Batch, BatchArgs, apply_overwrite_policy, gather_inputs, print_plan, rewrite_jobs,
+    single_output,
+};
+use crate::logging;
+use crate::walk::WalkArgs;
+
+#[derive(clap::Args, Debug)]
+#[command(group(
+    clap::ArgGroup::new("edits")
+        .args(["mipmaps", "resource_type", "top_mip"])
+        .required(true)
+        .multiple(true)
+))]
+pub struct EditArgs {
+    /// Textures (.tex) or folders to edit; folders are searched recursively
+    /// for .tex files. `-` reads a texture from stdin
+    #[arg(value_name = "INPUTS", required_unless_present = "input")]
+    pub inputs: Vec<String>,
+
+    /// Texture (.tex) to edit (alternative to positional INPUTS)
+    #[arg(short, long, value_name = "INPUT")]
+    pub input: Option<String>,
+
+    /// Output file path (only valid with a single input file).
+    /// Defaults to `<name>.edited.tex` next to each input (see `--in-place`).
+    /// `-` writes to stdout
+    #[arg(short, long, value_name = "OUTPUT")]
+    pub output: Option<String>,
+
+    /// Replace each input with its edited version
+    #[arg(long, conflicts_with_all = ["output", "overwrite"])]
+    pub in_place: bool,
+
+    /// Set or clear the mipmap flag. Clearing it drops every level but the top
+    /// one; setting it only works if the payload already holds the mip chain
+    #[arg(long, value_name = "BOOL")]
+    pub mipmaps: Option<bool>,
+
+    /// Change the resource type; the payload must already have the size the
+    /// new type calls for
+    #[arg(long, value_enum, value_name = "TYPE")]
+    pub resource_type: Option<ResourceKind>,
+
+    /// Make this mip level the top one, dropping the larger levels
+    #[arg(long, value_name = "LEVEL")]
+    pub top_mip: Option<u32>,
+
+    #[command(flatten)]
+    pub walk: WalkArgs,
+
+    #[command(flatten)]
+    pub batch: BatchArgs,
+}
+
+/// `--resource-type` values.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceKind {
+    Texture,
+    Cubemap,
+    Surface,
+    Volume,
+}
+
+impl From<ResourceKind> for ResourceType {
+    fn from(kind: ResourceKind) -> Self {
+        match kind {
+            ResourceKind::Texture => ResourceType::Texture,
+            ResourceKind::Cubemap => ResourceType::Cubemap,
+            ResourceKind::Surface => ResourceType::Surface,
+            ResourceKind::Volume => ResourceType::VolumeTexture,
+        }
+    }
+}
+
+/// Header changes to apply, in field order.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Edit {
+    pub top_mip: Option<u32>,
+    pub mipmaps: Option<bool>,
+    pub resource_type: Option<ResourceType>,
+}
+
+pub fn run(args: EditArgs) -> eyre::Result<()> {
+    let files = gather_inputs(
+        args.input.clone(),
+        args.inputs.clone(),
+        crate::commands::decode::DIR_EXTENSIONS,
+        &args.walk,
+    )?;
+    let output = single_output(args.output.clone(), &files)?;
+    let jobs = apply_overwrite_policy(
+        rewrite_jobs(&files, output.as_deref(), args.in_place, "edited"),
+        args.batch.overwrite,
+    );
+    if args.batch.dry_run {
+        print_plan(&jobs);
+        return Ok(());
+    }
+    if jobs.iter().any(|job| is_stdio(&job.output)) {
+        logging::reserve_stdout();
+    }
+
+    let changes = Edit {
+        top_mip: args.top_mip,
+        mipmaps: args.mipmaps,
+        resource_type: args.resource_type.map(ResourceType::from),
+    };
+    let batch = Batch {
+        command: "edit",
+        format: "tex".to_string(),
+        args: &args.batch.run,
+    };
+    batch.run(&jobs, |job| {
+        info!("editing {} -> {}", job.input.display(), job.output);
+        let bytes = read_input(&job.input.to_string_lossy())?;
+        let tex = edit(&Tex::from_reader(&mut bytes.as_slice())?, &changes)?;
+
+        let mut writer = create_output(&job.output)?;
+        tex.write(&mut writer)?;
+        Ok(writer.commit()?)
+    })
+}
+
+/// Apply `changes` to `tex`, erroring out if the result would not be a
+/// consistent texture (see [`texdata::verify`]).
+pub fn edit(tex: &Tex, changes: &Edit) -> eyre::Result<Tex> {
+    let mut header = Header::of(tex);
+    let mut payload = texdata::payload(tex);
+
+    if let Some(level) = changes.top_mip.filter(|level| *level > 0) {
+        if !header.has_mipmaps() {
+            eyre::bail!("--top-mip needs a texture with mipmaps");
+        }
+        let tex = texdata::drop_top_mips(&header, &payload, level)?;
+        (header, payload) = (Header::of(&tex), texdata::payload(&tex));
+    }
+
+    if let Some(mipmaps) = changes.mipmaps {
+        let old = header;
+        header.flags.set(TextureFlags::HasMipMaps, mipmaps);
+        if payload.len() != header.expected_len() {
+            if mipmaps {
+                eyre::bail!(
+                    "the payload holds {} byte(s) but a mip chain needs {}; \
+                     use `transcode --mipmaps generate` to build one",
+                    payload.len(),
+                    header.expected_len()
+                );
+            }
+            // Dropping a complete chain: keep the top level, which is stored last.
+            if old.has_mipmaps() && payload.len() >= old.expected_len() {
+                payload = payload[old.mip_range(0)].to_vec();
+            }
+        }
+    }
+
+    if let Some(resource_type) = changes.resource_type {
+        header.resource_type = resource_type;
+        if payload.len() != header.expected_len() {
+            eyre::bail!(
+                "a {resource_type:?} of this size needs {} byte(s) of data, the payload holds {}",
+                header.expected_len(),
+                payload.len()
+            );
+        }
+    }
+
+    let bytes = texdata::to_bytes(&header, &payload);
+    let problems = texdata::verify(&bytes);
+    if !problems.is_empty() {
+        eyre::bail!(
+            "the edited texture would be inconsistent: {}",
+            problems.join("; ")
+        );
+    }
+    Ok(Tex::from_reader(&mut bytes.as_slice())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use ltk_texture::tex::Format;
+
+    use super::*;
+
+    fn tex(resource_type: ResourceType, flags: TextureFlags, len_of: Option<Header>) -> Tex {
+        let header = Header {
+            width: 8,
+            height: 8,
+            depth: 1,
+            format: Format::Bgra8,
+            resource_type,
+            flags,
+        };
+        let len = len_of.unwrap_or(header).expected_len();
+        texdata::assemble(&header, &vec![0x80; len]).unwrap()
+    }
+
+    #[test]
+    fn clearing_the_mipmap_flag_keeps_the_top_level() {
+        let source = tex(ResourceType::Texture, TextureFlags::HasMipMaps, None);
+        let edited = edit(
+            &source,
+            &Edit {
+                mipmaps: Some(false),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(edited.mip_count, 1);
+        assert_eq!(texdata::payload(&edited).len(), 8 * 8 * 4);
+
+        // Setting it back needs data that is no longer there.
+        let back = Edit {
+            mipmaps: Some(true),
+            ..Default::default()
+        };
+        assert!(edit(&edited, &back).is_err());
+    }
+
+    #[test]
+    fn resource_type_must_match_the_payload() {
+        let cubemap_len = Header {
+            width: 8,
+            height: 8,
+            depth: 1,
+            format: Format::Bgra8,
+            resource_type: ResourceType::Cubemap,
+            flags: TextureFlags::empty(),
+        };
+        // A cubemap mislabelled as a 2D texture.
+        let source = tex(
+            ResourceType::Texture,
+            TextureFlags::empty(),
+            Some(cubemap_len),
+        );
+        let to = |resource_type| Edit {
+            resource_type: Some(resource_type),
+            ..Default::default()
+        };
+        let fixed = edit(&source, &to(ResourceType::Cubemap)).unwrap();
+        assert_eq!(fixed.resource_type, ResourceType::Cubemap);
+        assert!(edit(&fixed, &to(ResourceType::Texture)).is_err());
+    }
+}
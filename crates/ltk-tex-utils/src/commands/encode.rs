@@ -1,17 +1,29 @@
 use std::{
-    fs::{self, File},
-    io::{BufReader, BufWriter},
-    path::Path,
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
-use ltk_texture::{
-    Dds, Tex,
-    tex::{EncodeOptions, MipmapFilter},
+use image::ImageFormat;
+use ltk_tex_pipeline::encode::{
+    EncodeOptions, encode_cubemap, encode_file, encode_image, load_image, write_tex,
 };
+use ltk_tex_pipeline::format::{
+    ValidFormat, parse_format, parse_input_format, parse_mipmap_filter,
+};
+use ltk_tex_pipeline::io::is_stdio;
+use ltk_texture::tex::MipmapFilter;
 use tracing::info;
 
-use crate::batch::{gather_inputs, run_batch, sibling_with_extension, single_output};
-use crate::utils::{ValidFormat, parse_format, parse_mipmap_filter};
+use crate::batch::{
+    Batch, BatchArgs, Job, apply_overwrite_policy, gather_inputs, print_plan, sibling_jobs,
+    sibling_with_extension, single_output,
+};
+use crate::logging;
+use crate::wad;
+use crate::walk::WalkArgs;
+use crate::watch;
 
 /// File extensions picked up when a directory is passed to `encode`.
 pub const DIR_EXTENSIONS: &[&str] = &["png", "dds"];
@@ -19,7 +31,7 @@ pub const DIR_EXTENSIONS: &[&str] = &["png", "dds"];
 #[derive(clap::Args, Debug)]
 pub struct EncodeArgs {
     /// Images (.png/.dds/...) or folders to encode; folders are searched
-    /// recursively for .png/.dds files
+    /// recursively for .png/.dds files. `-` reads an image from stdin
     #[arg(value_name = "INPUTS", required_unless_present = "input")]
     pub inputs: Vec<String>,
 
@@ -29,9 +41,27 @@ pub struct EncodeArgs {
 
     /// Output file path (only valid with a single input file)
     /// Defaults to writing next to each input with a `.tex` extension.
+    /// `-` writes to stdout
     #[arg(short, long, value_name = "OUTPUT")]
     pub output: Option<String>,
 
+    /// Write the encoded textures into this WAD archive (.wad.client) instead of
+    /// next to the inputs. Entries of an existing archive are kept unless
+    /// replaced; nothing is written if any input fails
+    #[arg(long, value_name = "WAD", conflicts_with_all = ["output", "cubemap"])]
+    pub wad: Option<PathBuf>,
+
+    /// Folder the WAD entry paths are relative to: `<root>/assets/foo.png` is
+    /// stored as `assets/foo.tex`. Defaults to the current directory
+    #[arg(long, value_name = "DIR", requires = "wad", default_value = ".")]
+    pub wad_root: PathBuf,
+
+    /// Input image type (png, tga, dds, ...), for inputs whose extension doesn't
+    /// tell - notably stdin. Otherwise taken from the extension or sniffed from
+    /// the content
+    #[arg(long, value_name = "EXT", value_parser = parse_input_format)]
+    pub input_format: Option<ImageFormat>,
+
     /// Texture format to encode to
     /// (bc1, bc3, bc7, bgra8, rgba16f, rgba32f)
     #[arg(short, long, value_parser = parse_format, default_value = "bc3")]
@@ -50,80 +80,215 @@ pub struct EncodeArgs {
     /// Filter type to use for mipmap generation
     #[arg(long, default_value = "catmullrom", value_parser = parse_mipmap_filter)]
     pub mipmap_filter: MipmapFilter,
+
+    /// Build a single cubemap .tex from the inputs: either six face images
+    /// (+X, -X, +Y, -Y, +Z, -Z order) or one horizontal-cross image
+    #[arg(long, conflicts_with = "dry_run")]
+    pub cubemap: bool,
+
+    /// After encoding, keep watching the inputs and re-encode images as they
+    /// change (new files in watched folders included)
+    #[arg(long, conflicts_with_all = ["cubemap", "wad", "dry_run"])]
+    pub watch: bool,
+
+    /// Quiet period in milliseconds before `--watch` re-encodes changed files,
+    /// so a burst of saves triggers a single rebuild
+    #[arg(long, value_name = "MS", default_value_t = 300, requires = "watch")]
+    pub debounce: u64,
+
+    #[command(flatten)]
+    pub walk: WalkArgs,
+
+    #[command(flatten)]
+    pub batch: BatchArgs,
 }
 
 pub fn run(args: EncodeArgs) -> eyre::Result<()> {
-    let files = gather_inputs(args.input, args.inputs, DIR_EXTENSIONS)?;
-    let output = single_output(args.output, &files)?;
-    run_batch(&files, |file| {
-        let out = output
-            .clone()
-            .unwrap_or_else(|| sibling_with_extension(file, "tex"));
-        info!("encoding {} -> {}", file.display(), out);
-        encode(EncodeCommandOptions {
-            input: file.to_string_lossy().into_owned(),
-            output: out,
-            format: args.format,
-            weigh_color_by_alpha: args.weigh_color_by_alpha,
-            generate_mipmaps: args.generate_mipmaps,
-            mipmap_filter: args.mipmap_filter,
-        })
-    })
-}
+    if args.cubemap {
+        return run_cubemap(args);
+    }
 
-pub struct EncodeCommandOptions {
-    pub input: String,
-    pub output: String,
-    pub format: ValidFormat,
-    pub weigh_color_by_alpha: bool,
-    pub generate_mipmaps: bool,
-    pub mipmap_filter: MipmapFilter,
+    let files = gather_inputs(
+        args.input.clone(),
+        args.inputs.clone(),
+        DIR_EXTENSIONS,
+        &args.walk,
+    )?;
+    if let Some(wad_path) = &args.wad {
+        return run_wad(&files, wad_path, &args);
+    }
+    let output = single_output(args.output.clone(), &files)?;
+    let jobs = apply_overwrite_policy(
+        sibling_jobs(&files, output.as_deref(), "tex"),
+        args.batch.overwrite,
+    );
+    if args.batch.dry_run {
+        print_plan(&jobs);
+        return Ok(());
+    }
+    if args.watch {
+        return run_watch(&args, &jobs, output.as_deref());
+    }
+    if jobs.iter().any(|job| is_stdio(&job.output)) {
+        logging::reserve_stdout();
+    }
+    encode_jobs(&jobs, &args)
 }
 
-pub fn encode(options: EncodeCommandOptions) -> eyre::Result<()> {
-    let image = load_input_image(&options.input)?;
-
-    let tex = Tex::encode_rgba_image(
-        &image,
-        EncodeOptions {
-            format: options
-                .format
-                .to_encode_format(options.weigh_color_by_alpha),
-            generate_mipmaps: options.generate_mipmaps,
-            mipmap_filter: options.mipmap_filter,
-        },
-    )?;
+fn encode_jobs(jobs: &[Job], args: &EncodeArgs) -> eyre::Result<()> {
+    batch(args).run(jobs, |job| {
+        info!("encoding {} -> {}", job.input.display(), job.output);
+        Ok(encode_file(
+            &job.input.to_string_lossy(),
+            &job.output,
+            &options(args),
+        )?)
+    })
+}
 
-    let output_path = Path::new(&options.output);
-    if let Some(parent) = output_path.parent()
-        && !parent.as_os_str().is_empty()
+/// Encode everything once, then re-encode inputs as they change. Failures are
+/// logged and never end the watch.
+fn run_watch(args: &EncodeArgs, jobs: &[Job], output: Option<&str>) -> eyre::Result<()> {
+    if jobs
+        .iter()
+        .any(|job| is_stdio(&job.input) || is_stdio(&job.output))
     {
-        fs::create_dir_all(parent)?;
+        eyre::bail!("--watch cannot read from stdin or write to stdout");
+    }
+    let mut changes = watch::Changes::default();
+    changes.record(&jobs.iter().map(|job| job.input.clone()).collect::<Vec<_>>());
+    if let Err(err) = encode_jobs(jobs, args) {
+        tracing::error!("{err:#}");
+    }
+
+    let inputs = args
+        .input
+        .iter()
+        .chain(&args.inputs)
+        .cloned()
+        .collect::<Vec<_>>();
+    watch::watch(&inputs, Duration::from_millis(args.debounce), |changed| {
+        // Re-resolve the inputs so images added to a watched folder are picked up.
+        let files = match gather_inputs(
+            args.input.clone(),
+            args.inputs.clone(),
+            DIR_EXTENSIONS,
+            &args.walk,
+        ) {
+            Ok(files) => changes.take(files, changed),
+            Err(err) => {
+                tracing::error!("{err:#}");
+                return;
+            }
+        };
+        if files.is_empty() {
+            return;
+        }
+        info!("{} changed file(s), rebuilding", files.len());
+        let jobs =
+            apply_overwrite_policy(sibling_jobs(&files, output, "tex"), args.batch.overwrite);
+        match encode_jobs(&jobs, args) {
+            Ok(()) => info!("rebuilt {} file(s)", files.len()),
+            Err(err) => tracing::error!("{err:#}"),
+        }
+    })
+}
+
+/// Encode every input and store the results in the WAD at `wad_path`, once all
+/// of them succeeded.
+fn run_wad(files: &[PathBuf], wad_path: &Path, args: &EncodeArgs) -> eyre::Result<()> {
+    if files.iter().any(is_stdio) {
+        eyre::bail!("stdin has no path to store it under in a WAD");
+    }
+    // Entry paths are resolved up front so a wrong --wad-root fails before any work.
+    let mut entries = HashMap::new();
+    let mut jobs = Vec::with_capacity(files.len());
+    for file in files {
+        let entry = wad::entry_path(&file.with_extension("tex"), &args.wad_root)?;
+        jobs.push(Job {
+            input: file.clone(),
+            output: format!("{}:{entry}", wad_path.display()),
+        });
+        entries.insert(file.clone(), entry);
+    }
+    if args.batch.dry_run {
+        print_plan(&jobs);
+        return Ok(());
     }
-    let file = File::create(&options.output)?;
-    let mut writer = BufWriter::new(file);
 
-    tex.write(&mut writer)?;
+    let encoded = RefCell::new(BTreeMap::new());
+    batch(args).run(&jobs, |job| {
+        info!("encoding {} -> {}", job.input.display(), job.output);
+        let image = load_image(&job.input.to_string_lossy(), args.input_format)?;
+        let tex = encode_image(&image, &options(args))?;
+        let mut bytes = Vec::new();
+        tex.write(&mut bytes)?;
+        encoded
+            .borrow_mut()
+            .insert(entries[&job.input].clone(), bytes);
+        Ok(())
+    })?;
 
-    Ok(())
+    let encoded = encoded.into_inner();
+    info!(
+        "writing {} texture(s) into {}",
+        encoded.len(),
+        wad_path.display()
+    );
+    wad::write_textures(wad_path, &encoded)
 }
 
-/// Load the input as an RGBA8 image. DDS inputs are decoded through `ltk_texture`
-/// (top mip only), since the `image` crate cannot read block-compressed DDS.
-fn load_input_image(input: &str) -> eyre::Result<image::RgbaImage> {
-    let ext = Path::new(input)
-        .extension()
-        .and_then(|e| e.to_str())
-        .map(str::to_lowercase);
-
-    match ext.as_deref() {
-        Some("tex") => Err(eyre::eyre!("input is already a .tex texture: {input}")),
-        Some("dds") => {
-            let file = File::open(input)?;
-            let mut reader = BufReader::new(file);
-            let dds = Dds::from_reader(&mut reader)?;
-            Ok(dds.decode_mipmap(0)?.into_image()?)
-        }
-        _ => Ok(image::open(input)?.to_rgba8()),
+fn options(args: &EncodeArgs) -> EncodeOptions {
+    EncodeOptions {
+        input_format: args.input_format,
+        format: args.format,
+        weigh_color_by_alpha: args.weigh_color_by_alpha,
+        generate_mipmaps: args.generate_mipmaps,
+        mipmap_filter: args.mipmap_filter,
+    }
+}
+
+fn batch(args: &EncodeArgs) -> Batch<'_> {
+    Batch {
+        command: "encode",
+        format: args.format.name().to_string(),
+        args: &args.batch.run,
     }
 }
+
+fn run_cubemap(args: EncodeArgs) -> eyre::Result<()> {
+    let options = options(&args);
+    let mut inputs = args.inputs;
+    if let Some(flag) = args.input {
+        inputs.insert(0, flag);
+    }
+    let [first, ..] = inputs.as_slice() else {
+        eyre::bail!("missing input; pass six face images or one cross-layout image");
+    };
+
+    let job = Job {
+        input: PathBuf::from(first),
+        output: args
+            .output
+            .unwrap_or_else(|| sibling_with_extension(Path::new(first), "tex")),
+    };
+    let Some(Job { output, .. }) = apply_overwrite_policy(vec![job], args.batch.overwrite).pop()
+    else {
+        return Ok(());
+    };
+    if is_stdio(&output) {
+        logging::reserve_stdout();
+    }
+    info!("encoding cubemap {} -> {}", inputs.join(", "), output);
+
+    let images = match inputs.len() {
+        1 => vec![load_image(first, args.input_format)?],
+        6 => inputs
+            .iter()
+            .map(|input| load_image(input, args.input_format))
+            .collect::<ltk_tex_pipeline::Result<Vec<_>>>()?,
+        n => eyre::bail!("--cubemap needs six face images or one cross-layout image, got {n}"),
+    };
+    let tex = encode_cubemap(&images, &options)?;
+    Ok(write_tex(&tex, &output)?)
+}
@@ -0,0 +1,310 @@
+//! `flipbook`: slice a flipbook sheet (a grid of animation frames, as used by
+//! particle textures) and write the frames as an animated GIF, APNG or WebP.
+
+use std::io::Write;
+use std::path::Path;
+
+use image::codecs::webp::WebPEncoder;
+use image::{ExtendedColorType, RgbaImage, imageops};
+use ltk_tex_pipeline::io::{create_output, is_stdio, read_input};
+use ltk_tex_pipeline::layers;
+use ltk_texture::Tex;
+use tracing::info;
+
+use crate::batch::sibling_with_extension;
+use crate::logging;
+
+/// Largest `--cols`/`--rows`, which keeps `cols * rows` well within `u32`.
+const MAX_GRID: i64 = 16384;
+
+#[derive(clap::Args, Debug)]
+pub struct FlipbookArgs {
+    /// Flipbook texture (.tex). `-` reads a texture from stdin
+    #[arg(value_name = "INPUT", required_unless_present = "input")]
+    pub path: Option<String>,
+
+    /// Flipbook texture (.tex) (alternative to the positional INPUT)
+    #[arg(short, long, value_name = "INPUT", conflicts_with = "path")]
+    pub input: Option<String>,
+
+    /// Output file; its extension (.gif, .png/.apng, .webp) picks the format.
+    /// Defaults to the input with the `--format` extension. `-` writes to
+    /// stdout in the `--format` format
+    #[arg(short, long, value_name = "OUTPUT")]
+    pub output: Option<String>,
+
+    /// Animation format used when --output is not given or is `-`
+    #[arg(short, long, value_enum, default_value_t = AnimationFormat::Gif)]
+    pub format: AnimationFormat,
+
+    /// Number of frames per row of the sheet
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=MAX_GRID))]
+    pub cols: u32,
+
+    /// Number of rows of frames in the sheet
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=MAX_GRID))]
+    pub rows: u32,
+
+    /// Number of frames to play, read left to right then top to bottom.
+    /// Defaults to every cell of the grid
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub frames: Option<u32>,
+
+    /// Playback speed in frames per second
+    #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(u16).range(1..))]
+    pub fps: u16,
+}
+
+/// Animated image formats `flipbook` writes.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationFormat {
+    /// GIF (256 colors, 1-bit transparency, delays in 1/100 s)
+    Gif,
+    /// Animated PNG (lossless, full alpha)
+    Apng,
+    /// Animated WebP (lossless, full alpha)
+    Webp,
+}
+
+impl AnimationFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Gif => "gif",
+            Self::Apng => "png",
+            Self::Webp => "webp",
+        }
+    }
+
+    fn from_path(path: &Path) -> eyre::Result<Self> {
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("gif") => Ok(Self::Gif),
+            Some("png" | "apng") => Ok(Self::Apng),
+            Some("webp") => Ok(Self::Webp),
+            _ => eyre::bail!(
+                "cannot tell the animation format of {}; use a .gif, .png, .apng or .webp extension",
+                path.display()
+            ),
+        }
+    }
+}
+
+pub fn run(args: FlipbookArgs) -> eyre::Result<()> {
+    let input = args.input.or(args.path).expect("clap requires an input");
+    let format = match &args.output {
+        Some(output) if !is_stdio(output) => AnimationFormat::from_path(Path::new(output))?,
+        _ => args.format,
+    };
+    let output = args
+        .output
+        .unwrap_or_else(|| sibling_with_extension(Path::new(&input), format.extension()));
+    if is_stdio(&output) {
+        logging::reserve_stdout();
+    }
+
+    let bytes = read_input(&input)?;
+    let tex = Tex::from_reader(&mut bytes.as_slice())?;
+    let sheet = layers::decode_layer(&tex, 0, 0)?;
+    let frames = slice(
+        &sheet,
+        args.cols,
+        args.rows,
+        args.frames.unwrap_or(args.cols * args.rows),
+    )?;
+
+    let data = encode(&frames, format, args.fps)?;
+    let mut writer = create_output(&output)?;
+    writer.write_all(&data)?;
+    writer.commit()?;
+    info!(
+        "wrote {} frame(s) of {}x{} at {} fps to {output}",
+        frames.len(),
+        frames[0].width(),
+        frames[0].height(),
+        args.fps
+    );
+    Ok(())
+}
+
+/// The first `count` cells of a `cols` x `rows` grid over `sheet`, row by row.
+pub fn slice(sheet: &RgbaImage, cols: u32, rows: u32, count: u32) -> eyre::Result<Vec<RgbaImage>> {
+    let (width, height) = sheet.dimensions();
+    if !width.is_multiple_of(cols) || !height.is_multiple_of(rows) {
+        eyre::bail!("a {width}x{height} sheet cannot be split into {cols}x{rows} equal frames");
+    }
+    if count > cols * rows {
+        eyre::bail!(
+            "--frames {count} is more than the {} cells of a {cols}x{rows} grid",
+            cols * rows
+        );
+    }
+    let (frame_width, frame_height) = (width / cols, height / rows);
+    Ok((0..count)
+        .map(|i| {
+            let (x, y) = (i % cols * frame_width, i / cols * frame_height);
+            imageops::crop_imm(sheet, x, y, frame_width, frame_height).to_image()
+        })
+        .collect())
+}
+
+/// Encode equally sized `frames` as a looping animation. Each frame replaces
+/// the previous one, so transparent pixels never show earlier frames.
+pub fn encode(frames: &[RgbaImage], format: AnimationFormat, fps: u16) -> eyre::Result<Vec<u8>> {
+    let (width, height) = frames[0].dimensions();
+    let mut out = Vec::new();
+    match format {
+        AnimationFormat::Gif => {
+            let (gif_width, gif_height) = (u16::try_from(width)?, u16::try_from(height)?);
+            let mut encoder = gif::Encoder::new(&mut out, gif_width, gif_height, &[])?;
+            encoder.set_repeat(gif::Repeat::Infinite)?;
+            // Centiseconds, rounded; browsers treat delays below 2 as 10.
+            let delay = ((100 + fps / 2) / fps).max(2);
+            for frame in frames {
+                let mut pixels = frame.as_raw().clone();
+                let mut frame = gif::Frame::from_rgba_speed(gif_width, gif_height, &mut pixels, 10);
+                frame.delay = delay;
+                frame.dispose = gif::DisposalMethod::Background;
+                encoder.write_frame(&frame)?;
+            }
+        }
+        AnimationFormat::Apng => {
+            let mut encoder = png::Encoder::new(&mut out, width, height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_animated(frames.len() as u32, 0)?;
+            encoder.set_frame_delay(1, fps)?;
+            encoder.set_dispose_op(png::DisposeOp::Background)?;
+            encoder.set_blend_op(png::BlendOp::Source)?;
+            let mut writer = encoder.write_header()?;
+            for frame in frames {
+                writer.write_image_data(frame.as_raw())?;
+            }
+            writer.finish()?;
+        }
+        AnimationFormat::Webp => out = animated_webp(frames, fps)?,
+    }
+    Ok(out)
+}
+
+/// The `image` WebP encoder only writes still images, so wrap its lossless
+/// frames in the animation chunks (VP8X, ANIM, ANMF) of the extended format.
+fn animated_webp(frames: &[RgbaImage], fps: u16) -> eyre::Result<Vec<u8>> {
+    fn u24(value: u32) -> [u8; 3] {
+        let [a, b, c, _] = value.to_le_bytes();
+        [a, b, c]
+    }
+    fn chunk(name: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = [name.as_slice(), &(data.len() as u32).to_le_bytes(), data].concat();
+        if data.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    let (width, height) = frames[0].dimensions();
+    let duration = (1000 / fps as u32).max(1);
+    // Animation and alpha flags, then the canvas size.
+    let vp8x = [&[0x12, 0, 0, 0][..], &u24(width - 1), &u24(height - 1)].concat();
+    // Transparent background, loop forever.
+    let anim = [0u8; 6];
+    let mut body = [
+        b"WEBP".as_slice(),
+        &chunk(b"VP8X", &vp8x),
+        &chunk(b"ANIM", &anim),
+    ]
+    .concat();
+    for frame in frames {
+        let mut still = Vec::new();
+        WebPEncoder::new_lossless(&mut still).encode(
+            frame.as_raw(),
+            width,
+            height,
+            ExtendedColorType::Rgba8,
+        )?;
+        // A simple-format file: the 12-byte RIFF header, then the VP8L chunk.
+        let bitstream = still.get(12..).filter(|rest| rest.starts_with(b"VP8L"));
+        let bitstream = bitstream.ok_or_else(|| eyre::eyre!("unexpected WebP encoder output"))?;
+        // Offset (0, 0), size, duration, then "do not blend" and "dispose to background".
+        let header = [
+            &u24(0)[..],
+            &u24(0),
+            &u24(width - 1),
+            &u24(height - 1),
+            &u24(duration),
+            &[0b11],
+        ]
+        .concat();
+        body.extend(chunk(b"ANMF", &[header.as_slice(), bitstream].concat()));
+    }
+
+    let mut file = Vec::with_capacity(body.len() + 8);
+    file.write_all(b"RIFF")?;
+    file.write_all(&(body.len() as u32).to_le_bytes())?;
+    file.write_all(&body)?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::codecs::gif::GifDecoder;
+    use image::codecs::png::PngDecoder;
+    use image::codecs::webp::WebPDecoder;
+    use image::{AnimationDecoder, Frame, Rgba};
+
+    use super::*;
+
+    /// A 2x2 grid of 4x2 frames, each a flat color, the last one transparent.
+    fn sheet() -> RgbaImage {
+        const COLORS: [[u8; 4]; 4] = [
+            [255, 0, 0, 255],
+            [0, 255, 0, 255],
+            [0, 0, 255, 255],
+            [0, 0, 0, 0],
+        ];
+        RgbaImage::from_fn(8, 4, |x, y| Rgba(COLORS[(y / 2 * 2 + x / 4) as usize]))
+    }
+
+    #[test]
+    fn slices_frames_row_by_row() {
+        let frames = slice(&sheet(), 2, 2, 3).unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[2].dimensions(), (4, 2));
+        assert_eq!(frames[2].get_pixel(3, 1), &Rgba([0, 0, 255, 255]));
+
+        assert!(slice(&sheet(), 3, 2, 6).is_err());
+        assert!(slice(&sheet(), 2, 2, 5).is_err());
+    }
+
+    #[test]
+    fn encodes_every_format_as_an_animation() {
+        let frames = slice(&sheet(), 2, 2, 4).unwrap();
+        for format in [
+            AnimationFormat::Gif,
+            AnimationFormat::Apng,
+            AnimationFormat::Webp,
+        ] {
+            let data = Cursor::new(encode(&frames, format, 20).unwrap());
+            let decoded: Vec<Frame> = match format {
+                AnimationFormat::Gif => GifDecoder::new(data).unwrap().into_frames(),
+                AnimationFormat::Apng => {
+                    PngDecoder::new(data).unwrap().apng().unwrap().into_frames()
+                }
+                AnimationFormat::Webp => WebPDecoder::new(data).unwrap().into_frames(),
+            }
+            .collect_frames()
+            .unwrap();
+
+            assert_eq!(decoded.len(), 4, "{format:?}");
+            assert_eq!(decoded[0].delay().numer_denom_ms(), (50, 1), "{format:?}");
+            let last = decoded[3].buffer();
+            assert_eq!(last.dimensions(), (4, 2), "{format:?}");
+            // The transparent frame doesn't show the blue one underneath.
+            assert_eq!(last.get_pixel(0, 0)[3], 0, "{format:?}");
+            assert_eq!(decoded[1].buffer().get_pixel(0, 0), &Rgba([0, 255, 0, 255]));
+        }
+    }
+}
@@ -1,52 +1,87 @@
+use std::path::Path;
+
 use colored::Colorize;
+use eyre::WrapErr;
+use image::RgbaImage;
+use ltk_tex_pipeline::io::open_input;
+use ltk_tex_pipeline::layers;
 use ltk_texture::tex::Tex;
-use std::fs::File;
-use std::io::BufReader;
+
+use crate::stats::ImageStats;
+use crate::wad::{self, WadArgs, WadTextures};
 
 #[derive(clap::Args, Debug)]
 pub struct InfoArgs {
-    /// Texture to get info from
+    /// Texture to get info from (`-` reads from stdin). For a WAD archive
+    /// (.wad.client), every .tex entry is listed
     #[arg(short, long)]
     pub input: String,
+
+    /// Also decode a mip and print per-channel statistics, how alpha is used,
+    /// the number of unique colors and a histogram of each channel
+    #[arg(long)]
+    pub stats: bool,
+
+    /// Mipmap to compute `--stats` on (0 = largest)
+    #[arg(short, long, default_value = "0", requires = "stats")]
+    pub mipmap: u32,
+
+    #[command(flatten)]
+    pub wad: WadArgs,
 }
 
-pub fn run(args: InfoArgs) {
-    info(InfoCommandOptions { input: args.input });
+pub fn run(args: InfoArgs) -> eyre::Result<()> {
+    let stats = args.stats.then_some(args.mipmap);
+    if wad::is_wad(Path::new(&args.input)) {
+        return wad_info(&args.input, &args.wad, stats);
+    }
+    info(InfoCommandOptions {
+        input: args.input,
+        stats,
+    })
 }
 
 pub struct InfoCommandOptions {
     pub input: String,
+    /// Mip to print statistics of, if any.
+    pub stats: Option<u32>,
 }
 
-pub fn info(options: InfoCommandOptions) {
-    let path = &options.input;
-    let file = match File::open(path) {
-        Ok(f) => f,
-        Err(err) => {
-            eprintln!(
-                "{} failed to open '{}': {}",
-                "error:".bold().red(),
-                path,
-                err
-            );
-            return;
-        }
-    };
-    let mut reader = BufReader::new(file);
+/// List every texture of the WAD at `path`. Entries that fail are logged and
+/// the others still listed; the result is an error if any failed.
+fn wad_info(path: &str, args: &WadArgs, stats: Option<u32>) -> eyre::Result<()> {
+    let wad = WadTextures::open(Path::new(path), args)
+        .wrap_err_with(|| format!("failed to open WAD '{path}'"))?;
 
-    let tex = match Tex::from_reader(&mut reader) {
-        Ok(tex) => tex,
-        Err(err) => {
-            eprintln!(
-                "{} failed to read TEX from '{}': {:?}",
-                "error:".bold().red(),
-                path,
-                err
-            );
-            return;
+    let entries = wad.virtual_paths();
+    let mut failed = 0usize;
+    for entry in &entries {
+        let label = entry.to_string_lossy();
+        let result = wad
+            .read(entry)
+            .and_then(|data| Ok(Tex::from_reader(&mut data.as_slice())?))
+            .wrap_err_with(|| format!("failed to read TEX from '{label}'"))
+            .and_then(|tex| print_info(&label, &tex, stats));
+        if let Err(err) = result {
+            tracing::error!("{err:#}");
+            failed += 1;
         }
-    };
+    }
+    if failed > 0 {
+        eyre::bail!("{failed} of {} WAD entries failed", entries.len());
+    }
+    Ok(())
+}
 
+pub fn info(options: InfoCommandOptions) -> eyre::Result<()> {
+    let path = &options.input;
+    let mut reader = open_input(path).wrap_err_with(|| format!("failed to open '{path}'"))?;
+    let tex = Tex::from_reader(&mut reader)
+        .wrap_err_with(|| format!("failed to read TEX from '{path}'"))?;
+    print_info(path, &tex, options.stats)
+}
+
+fn print_info(path: &str, tex: &Tex, stats: Option<u32>) -> eyre::Result<()> {
     println!("{} {}", "info:".bold().blue(), path.bold());
     crate::println_pad!(
         "{} {}",
@@ -68,4 +103,66 @@ pub fn info(options: InfoCommandOptions) {
         "resource".bold().cyan(),
         format!(": {:?}", tex.resource_type).green()
     );
+
+    if let Some(level) = stats {
+        let image = decode_mip(tex, level)
+            .wrap_err_with(|| format!("failed to decode mip {level} of '{path}'"))?;
+        print_stats(level, &image);
+    }
+    Ok(())
+}
+
+/// Mip `level` with every face/slice of layered textures stacked vertically.
+fn decode_mip(tex: &Tex, level: u32) -> eyre::Result<RgbaImage> {
+    Ok(layers::stack(&layers::decode_level(tex, level)?))
+}
+
+fn print_stats(level: u32, image: &RgbaImage) {
+    let stats = ImageStats::of(image);
+    crate::println_pad!(
+        "{} {}",
+        "stats".bold().cyan(),
+        format!(": mip {} ({}x{})", level, image.width(), image.height()).green()
+    );
+    for (name, channel) in ["red", "green", "blue", "alpha"]
+        .iter()
+        .zip(&stats.channels)
+    {
+        crate::println_pad!(
+            "{} {}",
+            name.bold().cyan(),
+            format!(
+                ": min {}, max {}, mean {:.1}, stddev {:.1}",
+                channel.min, channel.max, channel.mean, channel.stddev
+            )
+            .green()
+        );
+    }
+    crate::println_pad!(
+        "{} {}",
+        "alpha use".bold().cyan(),
+        format!(": {}", stats.alpha.describe()).green()
+    );
+    crate::println_pad!(
+        "{} {}",
+        "unique colors".bold().cyan(),
+        format!(": {}", stats.unique_colors).green()
+    );
+    crate::println_pad!(
+        "{} {}",
+        "block format".bold().cyan(),
+        format!(
+            ": {} (smallest that keeps this alpha)",
+            stats.alpha.block_format().name()
+        )
+        .green()
+    );
+    crate::println_pad!(
+        "{} {}",
+        "histogram".bold().cyan(),
+        ": 0 to 255, 8 values per column"
+    );
+    for (name, channel) in ["R", "G", "B", "A"].iter().zip(&stats.channels) {
+        crate::println_pad!("  {} |{}|", name.bold().cyan(), channel.sparkline(32));
+    }
 }
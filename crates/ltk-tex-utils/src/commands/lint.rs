@@ -0,0 +1,413 @@
+//! `lint`: check textures against a packaging policy (size limits, mipmaps,
+//! formats, ...) configured per folder in a TOML file, suggesting how to fix
+//! each violation (mostly `transcode` or `downscale` flags).
+//!
+//! ```toml
+//! # Applies to every texture.
+//! [rules]
+//! max-size = 2048
+//! forbidden-formats = ["bgra8"]
+//! forbidden-formats-min-size = 256
+//!
+//! # Overrides for textures whose path matches; later sections win.
+//! [[folder]]
+//! path = "**/assets/ux/**"
+//! require-mipmaps = false
+//! power-of-two = false
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use globset::{Glob, GlobMatcher};
+use ltk_tex_pipeline::format::{ValidFormat, parse_format};
+use ltk_tex_pipeline::layers;
+use ltk_texture::Tex;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::batch::{Batch, Job, RunArgs, gather_inputs};
+use crate::wad::{TextureSources, WadArgs};
+use crate::walk::WalkArgs;
+
+/// Config file picked up from the working directory when `--config` isn't given.
+pub const DEFAULT_CONFIG: &str = "ltk-lint.toml";
+
+#[derive(clap::Args, Debug)]
+pub struct LintArgs {
+    /// Textures (.tex), folders or WAD archives (.wad.client) to lint; folders
+    /// are searched recursively for .tex files
+    #[arg(value_name = "INPUTS", required_unless_present = "input")]
+    pub inputs: Vec<String>,
+
+    /// Texture (.tex) to lint (alternative to positional INPUTS)
+    #[arg(short, long, value_name = "INPUT")]
+    pub input: Option<String>,
+
+    /// Lint policy (TOML). Defaults to `ltk-lint.toml` in the working
+    /// directory, or the built-in policy (mipmaps and power-of-two dimensions
+    /// required, no BC3 on opaque textures) if there is none
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub wad: WadArgs,
+
+    #[command(flatten)]
+    pub walk: WalkArgs,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+/// Lint rules; unset rules fall back to the enclosing section.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Rules {
+    /// Largest allowed width/height, in pixels.
+    pub max_size: Option<u32>,
+    pub require_mipmaps: Option<bool>,
+    pub power_of_two: Option<bool>,
+    /// Format names as accepted by `encode -f`.
+    pub forbidden_formats: Option<Vec<String>>,
+    /// Only forbid `forbidden_formats` for textures at least this large.
+    pub forbidden_formats_min_size: Option<u32>,
+    /// Flag BC3 textures whose alpha channel is fully opaque.
+    pub wasted_alpha: Option<bool>,
+}
+
+impl Rules {
+    fn builtin() -> Self {
+        Self {
+            require_mipmaps: Some(true),
+            power_of_two: Some(true),
+            wasted_alpha: Some(true),
+            ..Default::default()
+        }
+    }
+
+    /// Override the rules `other` sets.
+    fn overlay(&mut self, other: &Rules) {
+        macro_rules! overlay {
+            ($($field:ident),*) => {
+                $(if other.$field.is_some() {
+                    self.$field = other.$field.clone();
+                })*
+            };
+        }
+        overlay!(
+            max_size,
+            require_mipmaps,
+            power_of_two,
+            forbidden_formats,
+            forbidden_formats_min_size,
+            wasted_alpha
+        );
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    rules: Rules,
+    #[serde(default)]
+    folder: Vec<toml::Table>,
+}
+
+/// A loaded lint policy.
+pub struct Policy {
+    rules: Rules,
+    folders: Vec<(GlobMatcher, Rules)>,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            rules: Rules::builtin(),
+            folders: Vec::new(),
+        }
+    }
+}
+
+impl Policy {
+    pub fn parse(text: &str) -> eyre::Result<Self> {
+        let file: ConfigFile = toml::from_str(text)?;
+        let mut rules = Rules::builtin();
+        rules.overlay(&file.rules);
+        check_formats(&rules)?;
+
+        let mut folders = Vec::with_capacity(file.folder.len());
+        for mut table in file.folder {
+            let Some(toml::Value::String(path)) = table.remove("path") else {
+                eyre::bail!("every [[folder]] section needs a `path` glob");
+            };
+            let folder_rules: Rules = table.try_into()?;
+            check_formats(&folder_rules)?;
+            folders.push((Glob::new(&path)?.compile_matcher(), folder_rules));
+        }
+        Ok(Self { rules, folders })
+    }
+
+    fn load(path: Option<&Path>) -> eyre::Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(DEFAULT_CONFIG).is_file() => Path::new(DEFAULT_CONFIG),
+            None => return Ok(Self::default()),
+        };
+        let text = fs::read_to_string(path)
+            .map_err(|e| eyre::eyre!("failed to read lint config {}: {e}", path.display()))?;
+        Self::parse(&text).map_err(|e| eyre::eyre!("invalid lint config {}: {e}", path.display()))
+    }
+
+    /// Rules that apply to the texture at `path`.
+    pub fn rules_for(&self, path: &Path) -> Rules {
+        let path = path.to_string_lossy().replace('\\', "/");
+        let mut rules = self.rules.clone();
+        for (glob, folder_rules) in &self.folders {
+            if glob.is_match(&path) {
+                rules.overlay(folder_rules);
+            }
+        }
+        rules
+    }
+}
+
+fn check_formats(rules: &Rules) -> eyre::Result<()> {
+    for name in rules.forbidden_formats.iter().flatten() {
+        parse_format(name).map_err(|e| eyre::eyre!(e))?;
+    }
+    Ok(())
+}
+
+pub fn run(args: LintArgs) -> eyre::Result<()> {
+    let policy = Policy::load(args.config.as_deref())?;
+    let files = gather_inputs(
+        args.input,
+        args.inputs,
+        crate::commands::decode::DIR_EXTENSIONS,
+        &args.walk,
+    )?;
+    let (sources, textures) = TextureSources::open(files, &args.wad)?;
+    let jobs = textures
+        .into_iter()
+        .map(|input| Job {
+            input,
+            output: String::new(),
+        })
+        .collect::<Vec<_>>();
+    if jobs.is_empty() {
+        eyre::bail!("no textures to lint");
+    }
+
+    let batch = Batch {
+        command: "lint",
+        format: "tex".to_string(),
+        args: &args.run,
+    };
+    batch.run(&jobs, |job| {
+        let bytes = sources.read(&job.input)?;
+        let tex = Tex::from_reader(&mut bytes.as_slice())
+            .map_err(|e| eyre::eyre!("not a valid texture ({e}); run `verify` on it"))?;
+        let violations = lint(&tex, &policy.rules_for(&job.input))?;
+        if !violations.is_empty() {
+            eyre::bail!("{}", violations.join("; "));
+        }
+        info!("{}: ok", job.input.display());
+        Ok(())
+    })?;
+    info!("linted {} texture(s)", jobs.len());
+    Ok(())
+}
+
+/// Every rule `tex` breaks, each with a suggested fix.
+pub fn lint(tex: &Tex, rules: &Rules) -> eyre::Result<Vec<String>> {
+    let (width, height) = (tex.width as u32, tex.height as u32);
+    let size = width.max(height);
+    let format = ValidFormat::of(tex.format);
+    let mut violations = Vec::new();
+
+    if let Some(max) = rules.max_size
+        && size > max
+    {
+        violations.push(format!(
+            "{width}x{height} exceeds the maximum size of {max}px \
+             (fix: `downscale --max-size {max}`)"
+        ));
+    }
+    if rules.require_mipmaps == Some(true) && !tex.has_mipmaps() {
+        violations.push("has no mipmaps (fix: `transcode --mipmaps generate`)".to_string());
+    }
+    if rules.power_of_two == Some(true) && !(width.is_power_of_two() && height.is_power_of_two()) {
+        violations.push(format!(
+            "{width}x{height} is not a power of two (fix: resize the source to {}x{} before `encode`)",
+            nearest_power_of_two(width),
+            nearest_power_of_two(height)
+        ));
+    }
+
+    // Only decode the pixels when a rule needs them.
+    let mut opaque = None;
+    let mut is_opaque = || -> eyre::Result<bool> {
+        if opaque.is_none() {
+            opaque = Some(is_opaque(tex)?);
+        }
+        Ok(opaque == Some(true))
+    };
+
+    let forbidden = format.is_some_and(|format| {
+        rules
+            .forbidden_formats
+            .iter()
+            .flatten()
+            .any(|name| parse_format(name) == Ok(format))
+    });
+    if forbidden && size >= rules.forbidden_formats_min_size.unwrap_or(0) {
+        let fix = if is_opaque()? { "bc1" } else { "bc3" };
+        violations.push(format!(
+            "{:?} is not allowed here (fix: `transcode -f {fix}`)",
+            tex.format
+        ));
+    }
+    if rules.wasted_alpha == Some(true) && format == Some(ValidFormat::Bc3) && is_opaque()? {
+        violations.push(
+            "BC3 texture is fully opaque, its alpha channel wastes half the size \
+             (fix: `transcode -f bc1`)"
+                .to_string(),
+        );
+    }
+    Ok(violations)
+}
+
+/// Whether every pixel of the top mip (all faces/slices) is fully opaque.
+fn is_opaque(tex: &Tex) -> eyre::Result<bool> {
+    let images = layers::decode_level(tex, 0)?;
+    Ok(images
+        .iter()
+        .all(|image| image.pixels().all(|pixel| pixel[3] == u8::MAX)))
+}
+
+/// The power of two closest to `n` (rounding up on ties).
+fn nearest_power_of_two(n: u32) -> u32 {
+    let up = n.next_power_of_two();
+    let down = up >> 1;
+    if down > 0 && n - down < up - n {
+        down
+    } else {
+        up
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+    use ltk_tex_pipeline::texdata::{Header, assemble};
+    use ltk_texture::tex::{Format, ResourceType, TextureFlags};
+
+    use super::*;
+    use crate::cli::Args;
+
+    fn tex(width: u16, height: u16, format: Format, mipmaps: bool, alpha: u8) -> Tex {
+        let header = Header {
+            width,
+            height,
+            depth: 1,
+            format,
+            resource_type: ResourceType::Texture,
+            flags: if mipmaps {
+                TextureFlags::HasMipMaps
+            } else {
+                TextureFlags::empty()
+            },
+        };
+        let data = [0, 0, 0, alpha].repeat(header.expected_len() / 4);
+        assemble(&header, &data).unwrap()
+    }
+
+    #[test]
+    fn folder_sections_override_the_defaults() {
+        let policy = Policy::parse(
+            r#"
+            [rules]
+            max-size = 1024
+
+            [[folder]]
+            path = "**/ux/**"
+            require-mipmaps = false
+            max-size = 2048
+            "#,
+        )
+        .unwrap();
+        let rules = policy.rules_for(Path::new("mod/assets/ux/icon.tex"));
+        assert_eq!(rules.max_size, Some(2048));
+        assert_eq!(rules.require_mipmaps, Some(false));
+        assert_eq!(rules.power_of_two, Some(true));
+        let rules = policy.rules_for(Path::new("mod/assets/characters/a.tex"));
+        assert_eq!(rules.max_size, Some(1024));
+
+        assert!(Policy::parse("[rules]\nmax-sise = 1").is_err());
+        assert!(Policy::parse("[rules]\nforbidden-formats = [\"png\"]").is_err());
+        assert!(Policy::parse("[[folder]]\nmax-size = 1").is_err());
+    }
+
+    #[test]
+    fn reports_violations_with_fixes() {
+        let rules = Rules {
+            forbidden_formats: Some(vec!["bgra8".to_string()]),
+            forbidden_formats_min_size: Some(8),
+            ..Rules::builtin()
+        };
+        assert!(
+            lint(&tex(8, 8, Format::Bc1, true, 0), &rules)
+                .unwrap()
+                .is_empty()
+        );
+
+        let violations = lint(&tex(12, 8, Format::Bgra8, false, 255), &rules).unwrap();
+        assert_eq!(violations.len(), 3, "{violations:?}");
+        assert!(violations[0].contains("transcode --mipmaps generate"));
+        assert!(violations[1].contains("resize the source to 16x8"));
+        assert!(violations[2].contains("transcode -f bc1"));
+
+        // Small uncompressed textures are allowed.
+        assert!(
+            lint(&tex(4, 4, Format::Bgra8, true, 0), &rules)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn fixes_only_suggest_existing_commands_and_flags() {
+        let rules = Rules {
+            max_size: Some(4),
+            forbidden_formats: Some(vec!["bgra8".to_string()]),
+            ..Rules::builtin()
+        };
+        let mut violations = lint(&tex(12, 8, Format::Bgra8, false, 0), &rules).unwrap();
+        violations.extend(lint(&tex(8, 8, Format::Bc3, true, 255), &rules).unwrap());
+        assert_eq!(violations.len(), 5, "{violations:?}");
+
+        let cli = Args::command();
+        for violation in &violations {
+            // Commands are quoted in backticks: "(fix: `transcode -f bc1`)".
+            for fix in violation.split('`').skip(1).step_by(2) {
+                let mut words = fix.split_whitespace();
+                let name = words.next().unwrap();
+                let command = cli
+                    .find_subcommand(name)
+                    .unwrap_or_else(|| panic!("no `{name}` command: {violation}"));
+                for flag in words.filter(|word| word.starts_with('-')) {
+                    assert!(
+                        command.get_arguments().any(|arg| {
+                            flag.strip_prefix("--") == arg.get_long()
+                                || flag.strip_prefix('-').and_then(|s| s.chars().next())
+                                    == arg.get_short()
+                        }),
+                        "no `{flag}` flag on `{name}`: {violation}"
+                    );
+                }
+            }
+        }
+    }
+}
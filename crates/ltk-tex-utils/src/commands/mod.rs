@@ -1,7 +1,27 @@
 pub mod decode;
+pub mod dedupe;
+pub mod diff;
+pub mod downscale;
+pub mod edit;
 pub mod encode;
+pub mod flipbook;
 pub mod info;
+pub mod lint;
+pub mod montage;
+pub mod transcode;
+pub mod verify;
+pub mod view;
 
-pub use decode::{DecodeArgs, DecodeCommandOptions, decode};
-pub use encode::{EncodeArgs, EncodeCommandOptions, encode};
+pub use decode::DecodeArgs;
+pub use dedupe::DedupeArgs;
+pub use diff::DiffArgs;
+pub use downscale::DownscaleArgs;
+pub use edit::EditArgs;
+pub use encode::EncodeArgs;
+pub use flipbook::FlipbookArgs;
 pub use info::InfoArgs;
+pub use lint::LintArgs;
+pub use montage::MontageArgs;
+pub use transcode::TranscodeArgs;
+pub use verify::VerifyArgs;
+pub use view::ViewArgs;
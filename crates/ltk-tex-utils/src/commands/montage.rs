@@ -0,0 +1,232 @@
+//! `montage`: lay every texture of a folder (or WAD) out as captioned thumbnails
+//! on a single PNG contact sheet, for code reviews and release notes.
+
+use std::cell::RefCell;
+use std::io::{Cursor, Write};
+
+use image::{ImageFormat, Rgba, RgbaImage, imageops};
+use ltk_tex_handler_shared::scale_image;
+use ltk_tex_pipeline::format::format_name;
+use ltk_tex_pipeline::io::{create_output, is_stdio};
+use ltk_tex_pipeline::layers;
+use ltk_tex_pipeline::texdata::Header;
+use ltk_texture::Tex;
+use tracing::info;
+
+use crate::batch::{Batch, Job, RunArgs, gather_inputs};
+use crate::font::{self, GLYPH_HEIGHT};
+use crate::logging;
+use crate::termimage::on_checkerboard;
+use crate::wad::{TextureSources, WadArgs};
+use crate::walk::WalkArgs;
+
+/// Gap between thumbnails and around the sheet, in pixels.
+const PAD: u32 = 12;
+const BACKGROUND: Rgba<u8> = Rgba([0x20, 0x20, 0x20, 0xFF]);
+const NAME_COLOR: Rgba<u8> = Rgba([0xF0, 0xF0, 0xF0, 0xFF]);
+const DETAILS_COLOR: Rgba<u8> = Rgba([0x90, 0x90, 0x90, 0xFF]);
+
+#[derive(clap::Args, Debug)]
+pub struct MontageArgs {
+    /// Textures (.tex), folders or WAD archives (.wad.client) to put on the
+    /// sheet; folders are searched recursively for .tex files
+    #[arg(value_name = "INPUTS", required_unless_present = "input")]
+    pub inputs: Vec<String>,
+
+    /// Texture (.tex) or folder to put on the sheet (alternative to positional INPUTS)
+    #[arg(short, long, value_name = "INPUT")]
+    pub input: Option<String>,
+
+    /// Output PNG. `-` writes to stdout
+    #[arg(short, long, value_name = "OUTPUT", default_value = "montage.png")]
+    pub output: String,
+
+    /// Size of the longer side of each thumbnail, in pixels
+    #[arg(long, value_name = "PX", default_value_t = 256,
+          value_parser = clap::value_parser!(u32).range(16..=4096))]
+    pub tile: u32,
+
+    /// Number of thumbnails per row. Defaults to a roughly square sheet
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub columns: Option<u32>,
+
+    #[command(flatten)]
+    pub wad: WadArgs,
+
+    #[command(flatten)]
+    pub walk: WalkArgs,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+/// A scaled texture and its caption.
+pub struct Tile {
+    pub image: RgbaImage,
+    pub name: String,
+    /// Format and dimensions.
+    pub details: String,
+}
+
+pub fn run(args: MontageArgs) -> eyre::Result<()> {
+    let files = gather_inputs(
+        args.input,
+        args.inputs,
+        crate::commands::decode::DIR_EXTENSIONS,
+        &args.walk,
+    )?;
+    let (sources, textures) = TextureSources::open(files, &args.wad)?;
+    let jobs = textures
+        .into_iter()
+        .map(|input| Job {
+            input,
+            output: String::new(),
+        })
+        .collect::<Vec<_>>();
+    if jobs.is_empty() {
+        eyre::bail!("no textures to put on the sheet");
+    }
+    if is_stdio(&args.output) {
+        logging::reserve_stdout();
+    }
+
+    let tiles = RefCell::new(Vec::with_capacity(jobs.len()));
+    let batch = Batch {
+        command: "montage",
+        format: "png".to_string(),
+        args: &args.run,
+    };
+    // Failed textures are left off the sheet; the batch error is returned once
+    // the rest has been written.
+    let result = batch.run(&jobs, |job| {
+        let bytes = sources.read(&job.input)?;
+        let tex = Tex::from_reader(&mut bytes.as_slice())?;
+        let name = job.input.file_name().unwrap_or(job.input.as_os_str());
+        tiles
+            .borrow_mut()
+            .push(tile(&tex, &name.to_string_lossy(), args.tile)?);
+        Ok(())
+    });
+    let tiles = tiles.into_inner();
+    if tiles.is_empty() {
+        return result;
+    }
+
+    let columns = args
+        .columns
+        .unwrap_or_else(|| (tiles.len() as f64).sqrt().ceil() as u32);
+    let sheet = compose(&tiles, args.tile, columns);
+    let mut png = Vec::new();
+    sheet.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    let mut writer = create_output(&args.output)?;
+    writer.write_all(&png)?;
+    writer.commit()?;
+    info!(
+        "wrote {} ({}x{}, {} texture(s))",
+        args.output,
+        sheet.width(),
+        sheet.height(),
+        tiles.len()
+    );
+    result
+}
+
+/// Thumbnail of `tex` at most `size` pixels on its longer side (the first
+/// face/slice of layered textures).
+pub fn tile(tex: &Tex, name: &str, size: u32) -> eyre::Result<Tile> {
+    // Decode the smallest mip that is still at least `size` large.
+    let header = Header::of(tex);
+    let level = (0..header.mip_count())
+        .rev()
+        .find(|&level| {
+            let (width, height) = header.mip_dimensions(level);
+            width.max(height) >= size
+        })
+        .unwrap_or(0);
+    let image = layers::decode_layer(tex, level, 0)?;
+
+    let (pixels, width, height) = scale_image(image.as_raw(), image.width(), image.height(), size);
+    let image = RgbaImage::from_raw(width, height, pixels)
+        .ok_or_else(|| eyre::eyre!("scaled image has the wrong size"))?;
+    Ok(Tile {
+        image,
+        name: name.to_string(),
+        details: format!("{} {}x{}", format_name(tex.format), tex.width, tex.height),
+    })
+}
+
+/// Lay `tiles` out in rows of `columns`, each thumbnail centered in a `size`
+/// square over a checkerboard, with its name and details underneath.
+pub fn compose(tiles: &[Tile], size: u32, columns: u32) -> RgbaImage {
+    let columns = columns.clamp(1, tiles.len().max(1) as u32);
+    let rows = (tiles.len() as u32).div_ceil(columns);
+    let scale = if size >= 192 { 2 } else { 1 };
+    let line_height = (GLYPH_HEIGHT + 3) * scale;
+    let cell_height = size + PAD / 2 + 2 * line_height;
+
+    let mut sheet = RgbaImage::from_pixel(
+        PAD + columns * (size + PAD),
+        PAD + rows * (cell_height + PAD),
+        BACKGROUND,
+    );
+    for (i, tile) in tiles.iter().enumerate() {
+        let (column, row) = (i as u32 % columns, i as u32 / columns);
+        let (x, y) = (PAD + column * (size + PAD), PAD + row * (cell_height + PAD));
+        let (width, height) = tile.image.dimensions();
+        imageops::replace(
+            &mut sheet,
+            &on_checkerboard(&tile.image),
+            (x + (size - width) / 2) as i64,
+            (y + (size - height) / 2) as i64,
+        );
+
+        let caption_y = y + size + PAD / 2;
+        let name = font::fit_text(&tile.name, size, scale);
+        font::draw_text(&mut sheet, x, caption_y, &name, scale, NAME_COLOR);
+        let details = font::fit_text(&tile.details, size, scale);
+        let details_y = caption_y + line_height;
+        font::draw_text(&mut sheet, x, details_y, &details, scale, DETAILS_COLOR);
+    }
+    sheet
+}
+
+#[cfg(test)]
+mod tests {
+    use ltk_texture::tex::{EncodeFormat, EncodeOptions};
+
+    use super::*;
+
+    #[test]
+    fn lays_tiles_out_in_rows() {
+        let image = RgbaImage::from_pixel(64, 32, Rgba([255, 0, 0, 255]));
+        let tex = Tex::encode_rgba_image(
+            &image,
+            EncodeOptions::new(EncodeFormat::Bgra8).with_mipmaps(),
+        )
+        .unwrap();
+        let tile = tile(&tex, "red.tex", 16).unwrap();
+        assert_eq!(tile.image.dimensions(), (16, 8));
+        assert_eq!(tile.details, "BGRA8 64x32");
+
+        let tiles = [tile.image.clone(), tile.image.clone(), tile.image].map(|image| Tile {
+            image,
+            name: "red.tex".to_string(),
+            details: String::new(),
+        });
+        let sheet = compose(&tiles, 16, 2);
+        let cell_height = 16 + PAD / 2 + 2 * (GLYPH_HEIGHT + 3);
+        assert_eq!(
+            sheet.dimensions(),
+            (PAD + 2 * (16 + PAD), PAD + 2 * (cell_height + PAD))
+        );
+        // Thumbnails are centered in their square; the third starts a new row.
+        assert_eq!(sheet.get_pixel(PAD, PAD), &BACKGROUND);
+        assert_eq!(sheet.get_pixel(PAD, PAD + 4), &Rgba([255, 0, 0, 255]));
+        let second_row = PAD + cell_height + PAD;
+        assert_eq!(
+            sheet.get_pixel(PAD, second_row + 4),
+            &Rgba([255, 0, 0, 255])
+        );
+        assert_eq!(sheet.get_pixel(PAD + 16 + PAD, second_row + 4), &BACKGROUND);
+    }
+}
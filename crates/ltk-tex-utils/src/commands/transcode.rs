@@ -0,0 +1,201 @@
+//! `transcode`: re-encode `.tex` files to another format in memory, keeping
+//! their dimensions, resource type and (optionally) their mip chain.
+
+use ltk_tex_pipeline::format::{ValidFormat, parse_format, parse_mipmap_filter};
+use ltk_tex_pipeline::io::{create_output, is_stdio, read_input};
+use ltk_tex_pipeline::layers;
+use ltk_tex_pipeline::texdata::{self, Header};
+use ltk_texture::Tex;
+use ltk_texture::tex::{EncodeOptions, MipmapFilter, ResourceType, TextureFlags, encode_rgba};
+use tracing::info;
+
+use crate::batch::{
+    Batch, BatchArgs, apply_overwrite_policy, gather_inputs, print_plan, rewrite_jobs,
+    single_output,
+};
+use crate::logging;
+use crate::walk::WalkArgs;
+
+#[derive(clap::Args, Debug)]
+pub struct TranscodeArgs {
+    /// Textures (.tex) or folders to transcode; folders are searched
+    /// recursively for .tex files. `-` reads a texture from stdin
+    #[arg(value_name = "INPUTS", required_unless_present = "input")]
+    pub inputs: Vec<String>,
+
+    /// Texture (.tex) to transcode (alternative to positional INPUTS)
+    #[arg(short, long, value_name = "INPUT")]
+    pub input: Option<String>,
+
+    /// Output file path (only valid with a single input file).
+    /// Defaults to `<name>.<format>.tex` next to each input (see
+    /// `--in-place`). `-` writes to stdout
+    #[arg(short, long, value_name = "OUTPUT")]
+    pub output: Option<String>,
+
+    /// Replace each input with its transcoded version
+    #[arg(long, conflicts_with_all = ["output", "overwrite"])]
+    pub in_place: bool,
+
+    /// Texture format to transcode to
+    /// (bc1, bc3, bc7, bgra8, rgba16f, rgba32f)
+    #[arg(short, long, value_parser = parse_format)]
+    pub format: ValidFormat,
+
+    /// Weigh color by alpha during the BC1/BC3 cluster fit
+    /// (see `encode --weigh-color-by-alpha`)
+    #[arg(long, default_value = "false")]
+    pub weigh_color_by_alpha: bool,
+
+    /// What to do with the mip chain
+    #[arg(long, value_enum, default_value_t = MipmapMode::Keep)]
+    pub mipmaps: MipmapMode,
+
+    /// Filter type to use with `--mipmaps generate`
+    #[arg(long, default_value = "catmullrom", value_parser = parse_mipmap_filter)]
+    pub mipmap_filter: MipmapFilter,
+
+    #[command(flatten)]
+    pub walk: WalkArgs,
+
+    #[command(flatten)]
+    pub batch: BatchArgs,
+}
+
+/// How `transcode` treats the mip levels of its input.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MipmapMode {
+    /// Re-encode every existing level as is (textures without mipmaps stay so)
+    #[default]
+    Keep,
+    /// Regenerate the chain from the top level
+    Generate,
+    /// Drop every level but the top one
+    None,
+}
+
+pub fn run(args: TranscodeArgs) -> eyre::Result<()> {
+    let files = gather_inputs(
+        args.input.clone(),
+        args.inputs.clone(),
+        crate::commands::decode::DIR_EXTENSIONS,
+        &args.walk,
+    )?;
+    let output = single_output(args.output.clone(), &files)?;
+    let jobs = apply_overwrite_policy(
+        rewrite_jobs(&files, output.as_deref(), args.in_place, args.format.name()),
+        args.batch.overwrite,
+    );
+    if args.batch.dry_run {
+        print_plan(&jobs);
+        return Ok(());
+    }
+    if jobs.iter().any(|job| is_stdio(&job.output)) {
+        logging::reserve_stdout();
+    }
+
+    let options = EncodeOptions {
+        format: args.format.to_encode_format(args.weigh_color_by_alpha),
+        generate_mipmaps: false,
+        mipmap_filter: args.mipmap_filter,
+    };
+    let batch = Batch {
+        command: "transcode",
+        format: args.format.name().to_string(),
+        args: &args.batch.run,
+    };
+    batch.run(&jobs, |job| {
+        info!("transcoding {} -> {}", job.input.display(), job.output);
+        let bytes = read_input(&job.input.to_string_lossy())?;
+        let tex = Tex::from_reader(&mut bytes.as_slice())?;
+        let tex = transcode(&tex, &options, args.mipmaps)?;
+
+        let mut writer = create_output(&job.output)?;
+        tex.write(&mut writer)?;
+        Ok(writer.commit()?)
+    })
+}
+
+/// Decode `tex` and encode it again with `options` (whose `generate_mipmaps` is
+/// ignored in favor of `mipmaps`), keeping its dimensions and layers.
+pub fn transcode(tex: &Tex, options: &EncodeOptions, mipmaps: MipmapMode) -> eyre::Result<Tex> {
+    let source = Header::of(tex);
+    let mut flags = source.flags;
+    match mipmaps {
+        MipmapMode::Keep => {}
+        MipmapMode::Generate => flags.insert(TextureFlags::HasMipMaps),
+        MipmapMode::None => flags.remove(TextureFlags::HasMipMaps),
+    }
+    let header = Header {
+        format: options.format.into(),
+        flags,
+        ..source
+    };
+
+    // Images of every layer of every level, largest level first.
+    let levels = match mipmaps {
+        MipmapMode::Keep | MipmapMode::None => (0..header.mip_count())
+            .map(|level| layers::decode_level(tex, level))
+            .collect::<Result<Vec<_>, _>>()?,
+        MipmapMode::Generate => {
+            if source.resource_type == ResourceType::VolumeTexture {
+                eyre::bail!(
+                    "--mipmaps generate is not supported for volume textures; use keep or none"
+                );
+            }
+            let chains = layers::decode_level(tex, 0)?
+                .iter()
+                .map(|layer| layers::mip_chain(layer, header.mip_count(), options.mipmap_filter))
+                .collect::<Vec<_>>();
+            (0..header.mip_count() as usize)
+                .map(|level| chains.iter().map(|chain| chain[level].clone()).collect())
+                .collect()
+        }
+    };
+
+    let mut data = Vec::with_capacity(header.expected_len());
+    for level in levels.iter().rev() {
+        for layer in level {
+            data.extend(encode_rgba(
+                layer.width(),
+                layer.height(),
+                layer.as_raw(),
+                options,
+            )?);
+        }
+    }
+    Ok(texdata::assemble(&header, &data)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::RgbaImage;
+    use ltk_texture::tex::EncodeFormat;
+
+    use super::*;
+
+    #[test]
+    fn transcodes_keeping_dimensions_and_mips() {
+        let image = RgbaImage::from_fn(8, 4, |x, _| image::Rgba([x as u8 * 30, 0, 0, 255]));
+        let source = Tex::encode_rgba_image(
+            &image,
+            EncodeOptions::new(EncodeFormat::Bgra8).with_mipmaps(),
+        )
+        .unwrap();
+        let options = EncodeOptions::new(EncodeFormat::Bc1 {
+            weigh_colour_by_alpha: false,
+        });
+
+        let kept = transcode(&source, &options, MipmapMode::Keep).unwrap();
+        assert_eq!((kept.width, kept.height), (8, 4));
+        assert_eq!(kept.mip_count, source.mip_count);
+        assert_eq!(kept.format, ltk_texture::tex::Format::Bc1);
+        assert!(kept.decode_mipmap(kept.mip_count - 1).is_ok());
+
+        let dropped = transcode(&source, &options, MipmapMode::None).unwrap();
+        assert_eq!(dropped.mip_count, 1);
+
+        let back = transcode(&dropped, &options, MipmapMode::Generate).unwrap();
+        assert_eq!(back.mip_count, source.mip_count);
+    }
+}
@@ -0,0 +1,68 @@
+//! `verify`: check `.tex` files end to end so broken textures can be caught
+//! before a mod is released.
+
+use ltk_tex_pipeline::texdata::verify;
+use tracing::info;
+
+use crate::batch::{Batch, Job, RunArgs, gather_inputs};
+use crate::wad::{TextureSources, WadArgs};
+use crate::walk::WalkArgs;
+
+#[derive(clap::Args, Debug)]
+pub struct VerifyArgs {
+    /// Textures (.tex), folders or WAD archives (.wad.client) to check; folders
+    /// are searched recursively for .tex files, and every .tex entry of a WAD is
+    /// checked. `-` reads a texture from stdin
+    #[arg(value_name = "INPUTS", required_unless_present = "input")]
+    pub inputs: Vec<String>,
+
+    /// Texture (.tex) to check (alternative to positional INPUTS)
+    #[arg(short, long, value_name = "INPUT")]
+    pub input: Option<String>,
+
+    #[command(flatten)]
+    pub wad: WadArgs,
+
+    #[command(flatten)]
+    pub walk: WalkArgs,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+pub fn run(args: VerifyArgs) -> eyre::Result<()> {
+    let files = gather_inputs(
+        args.input,
+        args.inputs,
+        crate::commands::decode::DIR_EXTENSIONS,
+        &args.walk,
+    )?;
+    let (sources, textures) = TextureSources::open(files, &args.wad)?;
+    let jobs = textures
+        .into_iter()
+        .map(|input| Job {
+            input,
+            output: String::new(),
+        })
+        .collect::<Vec<_>>();
+    if jobs.is_empty() {
+        eyre::bail!("no textures to verify");
+    }
+
+    let batch = Batch {
+        command: "verify",
+        format: "tex".to_string(),
+        args: &args.run,
+    };
+    batch.run(&jobs, |job| {
+        let bytes = sources.read(&job.input)?;
+        let problems = verify(&bytes);
+        if !problems.is_empty() {
+            eyre::bail!("{}", problems.join("; "));
+        }
+        info!("{}: ok", job.input.display());
+        Ok(())
+    })?;
+    info!("verified {} texture(s)", jobs.len());
+    Ok(())
+}
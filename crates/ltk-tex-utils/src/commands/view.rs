@@ -0,0 +1,108 @@
+//! `view`: show a `.tex` in the terminal, followed by the metadata lines the
+//! Explorer preview pane overlays on it.
+
+use std::io::{self, IsTerminal, Write};
+
+use console::Term;
+use image::RgbaImage;
+use ltk_tex_handler_shared::TexMeta;
+use ltk_tex_pipeline::format::format_name;
+use ltk_tex_pipeline::io::read_input;
+use ltk_tex_pipeline::layers;
+use ltk_texture::Tex;
+
+use crate::termimage::{self, Protocol};
+
+/// Lines kept free under the image for the metadata and the prompt.
+const META_ROWS: u32 = 6;
+
+#[derive(clap::Args, Debug)]
+pub struct ViewArgs {
+    /// Texture (.tex) to show. `-` reads a texture from stdin
+    #[arg(value_name = "INPUT", required_unless_present = "input")]
+    pub path: Option<String>,
+
+    /// Texture (.tex) to show (alternative to the positional INPUT)
+    #[arg(short, long, value_name = "INPUT", conflicts_with = "path")]
+    pub input: Option<String>,
+
+    /// Mipmap to show (0 = largest)
+    #[arg(short, long, default_value = "0")]
+    pub mipmap: u32,
+
+    /// Cubemap face or volume slice to show
+    #[arg(long, default_value = "0")]
+    pub layer: u32,
+
+    /// Width of the image in terminal columns. Defaults to one column per
+    /// pixel (about 8 pixels per column for graphics protocols), capped at the
+    /// terminal width
+    #[arg(long, value_name = "COLUMNS")]
+    pub width: Option<u32>,
+
+    /// How to draw the image
+    #[arg(long, value_enum, default_value_t = Protocol::Auto)]
+    pub protocol: Protocol,
+}
+
+pub fn run(args: ViewArgs) -> eyre::Result<()> {
+    let input = args.input.or(args.path).expect("clap requires an input");
+    let bytes = read_input(&input)?;
+    let tex = Tex::from_reader(&mut bytes.as_slice())?;
+    let image = decode(&tex, args.mipmap, args.layer)?;
+
+    let stdout = io::stdout();
+    let is_terminal = stdout.is_terminal();
+    let protocol = args.protocol.resolve(is_terminal);
+    let size = is_terminal.then(|| Term::stdout().size_checked()).flatten();
+    let (columns, rows) = match (args.width, size) {
+        (Some(width), _) => (width, None),
+        (None, Some((rows, columns))) => (
+            termimage::natural_columns(&image, protocol, columns as u32),
+            Some((rows as u32).saturating_sub(META_ROWS).max(1)),
+        ),
+        (None, None) => (termimage::natural_columns(&image, protocol, 80), None),
+    };
+
+    let mut lines = meta(&tex, &image).lines();
+    if args.mipmap > 0 || layers::is_layered(&tex) {
+        lines.push(format!(
+            "Showing: mip {} ({} x {}){}",
+            args.mipmap,
+            image.width(),
+            image.height(),
+            if layers::is_layered(&tex) {
+                format!(", {}", layers::layer_suffix(tex.resource_type, args.layer))
+            } else {
+                String::new()
+            }
+        ));
+    }
+
+    let mut out = stdout.lock();
+    out.write_all(termimage::render(&image, protocol, columns, rows)?.as_bytes())?;
+    for line in lines {
+        writeln!(out, "{line}")?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Decode face/slice `layer` of mip `level`.
+fn decode(tex: &Tex, level: u32, layer: u32) -> eyre::Result<RgbaImage> {
+    if layer > 0 && !layers::is_layered(tex) {
+        eyre::bail!("--layer only applies to cubemaps and volume textures");
+    }
+    Ok(layers::decode_layer(tex, level, layer)?)
+}
+
+/// The preview pane metadata of `tex`, with alpha judged from the shown image.
+fn meta(tex: &Tex, image: &RgbaImage) -> TexMeta {
+    TexMeta {
+        format: format_name(tex.format),
+        width: tex.width as u32,
+        height: tex.height as u32,
+        mip_count: tex.mip_count,
+        has_alpha: image.pixels().any(|pixel| pixel[3] != u8::MAX),
+    }
+}
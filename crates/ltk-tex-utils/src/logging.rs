@@ -1,37 +1,213 @@
+use std::fs::OpenOptions;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use indicatif::ProgressBar;
+
 use tracing::Level;
 use tracing::level_filters::LevelFilter;
+use tracing_subscriber::fmt::MakeWriter;
 use tracing_subscriber::prelude::*;
+use tracing_subscriber::{EnvFilter, Layer, Registry};
+
+/// Set once stdout carries texture data (`-o -`); logs then all go to stderr.
+static STDOUT_RESERVED: AtomicBool = AtomicBool::new(false);
+
+/// Route every log line to stderr from now on, keeping stdout clean for data.
+pub fn reserve_stdout() {
+    STDOUT_RESERVED.store(true, Ordering::Relaxed);
+}
+
+/// Progress bar currently drawn on the terminal, if any (see `progress`).
+static PROGRESS_BAR: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+/// Register the progress bar log lines must not be mixed into, or clear it.
+pub fn set_progress_bar(bar: Option<ProgressBar>) {
+    *PROGRESS_BAR.lock().unwrap_or_else(|e| e.into_inner()) = bar;
+}
+
+/// Writes log lines with the progress bar (if any) hidden, so they end up
+/// above it instead of garbling it.
+struct AboveProgressBar<W>(W);
+
+impl<W: Write> AboveProgressBar<W> {
+    fn with_bar_hidden<R>(&mut self, f: impl FnOnce(&mut W) -> R) -> R {
+        let bar = PROGRESS_BAR
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        match bar {
+            Some(bar) => bar.suspend(|| f(&mut self.0)),
+            None => f(&mut self.0),
+        }
+    }
+}
+
+impl<W: Write> Write for AboveProgressBar<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.with_bar_hidden(|w| w.write(buf))
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.with_bar_hidden(|w| w.write_all(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.with_bar_hidden(|w| w.flush())
+    }
+}
+
+fn info_writer() -> Box<dyn Write> {
+    if STDOUT_RESERVED.load(Ordering::Relaxed) {
+        Box::new(AboveProgressBar(io::stderr()))
+    } else {
+        Box::new(AboveProgressBar(io::stdout()))
+    }
+}
+
+fn error_writer() -> AboveProgressBar<io::Stderr> {
+    AboveProgressBar(io::stderr())
+}
+
+// Global options controlling what is logged and where. Not a doc comment:
+// clap would take it as the program's about text.
+#[derive(clap::Args, Debug, Clone, Default)]
+#[command(next_help_heading = "Logging")]
+pub struct LogArgs {
+    /// Log more (`-v` debug, `-vv` trace). `RUST_LOG` overrides the level when
+    /// neither `-v` nor `-q` is given
+    #[arg(short, long, global = true, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Log less (`-q` warnings and errors only, `-qq` errors only)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub quiet: u8,
+
+    /// Format of log lines
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Human)]
+    pub log_format: LogFormat,
+
+    /// Also append every log line to this file (without colors)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Timestamped, colored lines
+    #[default]
+    Human,
+    /// One JSON object per line
+    Json,
+}
+
+impl LogArgs {
+    /// Level of this tool's own logs. Other crates never log below INFO unless
+    /// asked to through `RUST_LOG`.
+    fn level(&self) -> LevelFilter {
+        match (self.verbose, self.quiet) {
+            (0, 0) => LevelFilter::INFO,
+            (1, _) => LevelFilter::DEBUG,
+            (_, 0) => LevelFilter::TRACE,
+            (_, 1) => LevelFilter::WARN,
+            _ => LevelFilter::ERROR,
+        }
+    }
 
-pub fn init() {
-    let common_format = tracing_subscriber::fmt::format()
-        .with_ansi(true)
-        .with_level(true)
-        .with_source_location(false)
-        .with_line_number(false)
+    fn filter(&self) -> EnvFilter {
+        if self.verbose == 0
+            && self.quiet == 0
+            && let Ok(filter) = EnvFilter::try_from_default_env()
+        {
+            return filter;
+        }
+        let level = self.level();
+        EnvFilter::new(format!(
+            "{},{}={level}",
+            level.min(LevelFilter::INFO),
+            env!("CARGO_CRATE_NAME")
+        ))
+    }
+}
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+fn fmt_layer<W>(writer: W, format: LogFormat, ansi: bool) -> BoxedLayer
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
         .with_target(false)
         .with_timer(tracing_subscriber::fmt::time::time());
+    match format {
+        LogFormat::Human => layer.with_ansi(ansi).boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
+
+pub fn init(args: &LogArgs) -> eyre::Result<()> {
+    let ansi = std::env::var_os("NO_COLOR").is_none();
 
-    // stdout: INFO/DEBUG/TRACE
-    let stdout_layer = tracing_subscriber::fmt::layer()
-        .with_writer(std::io::stdout)
-        .event_format(common_format.clone())
-        .with_filter(tracing_subscriber::filter::filter_fn(|metadata| {
-            let level = *metadata.level();
-            level == Level::INFO || level == Level::DEBUG || level == Level::TRACE
-        }));
+    // stdout: INFO/DEBUG/TRACE (stderr while stdout is reserved for data)
+    let stdout_layer = fmt_layer(
+        info_writer,
+        args.log_format,
+        ansi && io::stdout().is_terminal(),
+    )
+    .with_filter(tracing_subscriber::filter::filter_fn(|metadata| {
+        let level = *metadata.level();
+        level == Level::INFO || level == Level::DEBUG || level == Level::TRACE
+    }));
 
     // stderr: WARN/ERROR
-    let stderr_layer = tracing_subscriber::fmt::layer()
-        .with_writer(std::io::stderr)
-        .event_format(common_format)
-        .with_filter(tracing_subscriber::filter::filter_fn(|metadata| {
-            let level = *metadata.level();
-            level == Level::WARN || level == Level::ERROR
-        }));
+    let stderr_layer = fmt_layer(
+        error_writer,
+        args.log_format,
+        ansi && io::stderr().is_terminal(),
+    )
+    .with_filter(tracing_subscriber::filter::filter_fn(|metadata| {
+        let level = *metadata.level();
+        level == Level::WARN || level == Level::ERROR
+    }));
+
+    let mut layers: Vec<BoxedLayer> = vec![stdout_layer.boxed(), stderr_layer.boxed()];
+    if let Some(path) = &args.log_file {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| eyre::eyre!("failed to open log file {}: {e}", path.display()))?;
+        layers.push(fmt_layer(Mutex::new(file), args.log_format, false));
+    }
 
     tracing_subscriber::registry()
-        .with(stdout_layer)
-        .with(stderr_layer)
-        .with(LevelFilter::TRACE)
+        .with(layers)
+        .with(args.filter())
         .init();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verbosity_flags_pick_the_level() {
+        let level = |verbose, quiet| {
+            LogArgs {
+                verbose,
+                quiet,
+                ..Default::default()
+            }
+            .level()
+        };
+        assert_eq!(level(0, 0), LevelFilter::INFO);
+        assert_eq!(level(1, 0), LevelFilter::DEBUG);
+        assert_eq!(level(3, 0), LevelFilter::TRACE);
+        assert_eq!(level(0, 1), LevelFilter::WARN);
+        assert_eq!(level(0, 2), LevelFilter::ERROR);
+    }
 }
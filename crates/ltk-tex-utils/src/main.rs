@@ -1,24 +1,33 @@
 use std::ops::ControlFlow;
+use std::process::ExitCode;
 
 mod auto_mode;
 mod batch;
 mod cli;
 mod commands;
+mod font;
 mod handler;
 mod logging;
+mod progress;
 mod shell;
+mod stats;
+mod termimage;
 mod utils;
+mod wad;
+mod walk;
+mod watch;
 
 use cli::PauseMode;
 
-fn main() -> eyre::Result<()> {
-    logging::init();
-
+fn main() -> ExitCode {
     if let ControlFlow::Break(result) = auto_mode::try_handle() {
         return cli::finish(result, PauseMode::OnError);
     }
 
     let args = cli::parse();
     let pause = args.pause;
+    if let Err(err) = logging::init(&args.log) {
+        return cli::finish(Err(err), pause);
+    }
     cli::finish(cli::run(args.command), pause)
 }
@@ -0,0 +1,99 @@
+//! Progress display for batch runs: a progress bar on terminals, periodic log
+//! lines otherwise (e.g. when output is redirected to a file).
+
+use std::io::IsTerminal;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+use crate::logging;
+
+/// How often progress is logged when there is no terminal to draw a bar on.
+const LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+pub struct Progress {
+    bar: Option<ProgressBar>,
+    total: usize,
+    done: usize,
+    started: Instant,
+    last_log: Instant,
+}
+
+impl Progress {
+    /// Start tracking a batch of `total` files. A bar is only drawn for
+    /// multi-file batches when both stdout and stderr are terminals.
+    pub fn start(total: usize) -> Self {
+        let interactive = std::io::stdout().is_terminal() && std::io::stderr().is_terminal();
+        let bar = (interactive && total > 1).then(|| {
+            let bar =
+                ProgressBar::with_draw_target(Some(total as u64), ProgressDrawTarget::stderr());
+            bar.set_style(
+                ProgressStyle::with_template(
+                    "{spinner:.green} [{elapsed_precise}] {bar:30.cyan/blue} {pos}/{len} \
+                     ({per_sec}, ETA {eta}) {wide_msg}",
+                )
+                .expect("valid template")
+                .progress_chars("=> "),
+            );
+            bar.enable_steady_tick(Duration::from_millis(100));
+            logging::set_progress_bar(Some(bar.clone()));
+            bar
+        });
+
+        let now = Instant::now();
+        Self {
+            bar,
+            total,
+            done: 0,
+            started: now,
+            last_log: now,
+        }
+    }
+
+    /// `input` is being converted.
+    pub fn begin(&self, input: &Path) {
+        if let Some(bar) = &self.bar {
+            bar.set_message(input.display().to_string());
+        }
+    }
+
+    /// The current file is done (successfully or not).
+    pub fn advance(&mut self) {
+        self.done += 1;
+        if let Some(bar) = &self.bar {
+            bar.inc(1);
+        } else if self.total > 1
+            && (self.last_log.elapsed() >= LOG_INTERVAL || self.done == self.total)
+        {
+            self.last_log = Instant::now();
+            tracing::info!("progress: {}", self.summary());
+        }
+    }
+
+    /// `done/total (rate, ETA)` for log lines.
+    fn summary(&self) -> String {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let rate = if elapsed > 0.0 {
+            self.done as f64 / elapsed
+        } else {
+            0.0
+        };
+        let remaining = self.total - self.done;
+        let eta = if rate > 0.0 {
+            format!("ETA {}s", (remaining as f64 / rate).ceil() as u64)
+        } else {
+            "ETA unknown".to_string()
+        };
+        format!("{}/{} file(s) ({rate:.1}/s, {eta})", self.done, self.total)
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        if let Some(bar) = self.bar.take() {
+            logging::set_progress_bar(None);
+            bar.finish_and_clear();
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Channel statistics of a decoded image (`info --stats`): per-channel range,
+//! mean, spread and histogram, how the alpha channel is used and how many
+//! distinct colors there are.
+
+use image::RgbaImage;
+use ltk_tex_pipeline::format::ValidFormat;
+
+/// Characters of increasing density for one histogram bucket.
+const RAMP: &[u8] = b" .:-=+*#%@";
+
+/// Statistics of one channel over every pixel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelStats {
+    pub min: u8,
+    pub max: u8,
+    pub mean: f64,
+    pub stddev: f64,
+    /// Number of pixels per value.
+    pub histogram: [u64; 256],
+}
+
+/// How a texture uses its alpha channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlphaKind {
+    /// Every pixel is fully opaque.
+    Opaque,
+    /// Every pixel is fully opaque or fully transparent (cutouts).
+    OneBit,
+    /// Partially transparent pixels (soft edges, fades).
+    Gradient,
+}
+
+impl AlphaKind {
+    pub fn describe(self) -> &'static str {
+        match self {
+            AlphaKind::Opaque => "opaque",
+            AlphaKind::OneBit => "1-bit (only fully opaque or transparent)",
+            AlphaKind::Gradient => "gradient (partial transparency)",
+        }
+    }
+
+    /// The block-compressed format that keeps this alpha intact at the
+    /// smallest size: BC1 covers opaque and cutout alpha, soft alpha needs BC3.
+    pub fn block_format(self) -> ValidFormat {
+        match self {
+            AlphaKind::Opaque | AlphaKind::OneBit => ValidFormat::Bc1,
+            AlphaKind::Gradient => ValidFormat::Bc3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageStats {
+    /// Red, green, blue and alpha.
+    pub channels: [ChannelStats; 4],
+    pub alpha: AlphaKind,
+    /// Number of distinct RGBA values.
+    pub unique_colors: usize,
+}
+
+impl ImageStats {
+    pub fn of(image: &RgbaImage) -> Self {
+        let mut histograms = [[0u64; 256]; 4];
+        for pixel in image.pixels() {
+            for (histogram, &value) in histograms.iter_mut().zip(&pixel.0) {
+                histogram[value as usize] += 1;
+            }
+        }
+        let channels = histograms.map(ChannelStats::from_histogram);
+
+        let alpha = &channels[3].histogram;
+        let alpha = if alpha[..255].iter().all(|&count| count == 0) {
+            AlphaKind::Opaque
+        } else if alpha[1..255].iter().all(|&count| count == 0) {
+            AlphaKind::OneBit
+        } else {
+            AlphaKind::Gradient
+        };
+
+        let mut colors = image
+            .pixels()
+            .map(|pixel| u32::from_le_bytes(pixel.0))
+            .collect::<Vec<_>>();
+        colors.sort_unstable();
+        colors.dedup();
+
+        Self {
+            channels,
+            alpha,
+            unique_colors: colors.len(),
+        }
+    }
+}
+
+impl ChannelStats {
+    fn from_histogram(histogram: [u64; 256]) -> Self {
+        let count = histogram.iter().sum::<u64>().max(1) as f64;
+        let used = || histogram.iter().enumerate().filter(|(_, n)| **n > 0);
+        let mean = used().map(|(v, &n)| v as f64 * n as f64).sum::<f64>() / count;
+        let variance = used()
+            .map(|(v, &n)| (v as f64 - mean).powi(2) * n as f64)
+            .sum::<f64>()
+            / count;
+        Self {
+            min: used().next().map_or(0, |(v, _)| v as u8),
+            max: used().next_back().map_or(0, |(v, _)| v as u8),
+            mean,
+            stddev: variance.sqrt(),
+            histogram,
+        }
+    }
+
+    /// The histogram folded into `buckets` equal ranges of values, each drawn
+    /// as one character whose density follows its share of the fullest bucket.
+    pub fn sparkline(&self, buckets: usize) -> String {
+        let width = 256 / buckets;
+        let counts = self
+            .histogram
+            .chunks(width)
+            .map(|chunk| chunk.iter().sum::<u64>())
+            .collect::<Vec<_>>();
+        let peak = counts.iter().copied().max().unwrap_or(0).max(1);
+        counts
+            .iter()
+            .map(|&count| {
+                let level = if count == 0 {
+                    0
+                } else {
+                    // Any non-empty bucket is visible.
+                    1 + (count * (RAMP.len() as u64 - 2) / peak) as usize
+                };
+                RAMP[level] as char
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    #[test]
+    fn measures_channels_and_alpha() {
+        let mut image = RgbaImage::from_pixel(2, 2, Rgba([0, 128, 255, 255]));
+        image.put_pixel(1, 1, Rgba([100, 128, 255, 0]));
+        let stats = ImageStats::of(&image);
+
+        let red = &stats.channels[0];
+        assert_eq!((red.min, red.max, red.mean), (0, 100, 25.0));
+        assert!((red.stddev - 1875f64.sqrt()).abs() < 1e-9);
+        assert_eq!(stats.channels[1].stddev, 0.0);
+        assert_eq!(stats.alpha, AlphaKind::OneBit);
+        assert_eq!(stats.unique_colors, 2);
+        assert_eq!(red.sparkline(4), "@-  ");
+
+        image.put_pixel(0, 0, Rgba([0, 128, 255, 128]));
+        assert_eq!(ImageStats::of(&image).alpha, AlphaKind::Gradient);
+        assert_eq!(
+            ImageStats::of(&RgbaImage::new(1, 1)).alpha.block_format(),
+            ValidFormat::Bc1
+        );
+    }
+}
@@ -0,0 +1,282 @@
+//! Draw RGBA images in a terminal: Unicode half blocks with truecolor escapes
+//! everywhere, or the kitty, iTerm2 and sixel inline graphics protocols on
+//! terminals that have them.
+
+use std::env;
+use std::io::Cursor;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use image::imageops::{self, FilterType};
+use image::{ImageFormat, Rgba, RgbaImage};
+
+/// Cell width assumed when sizing pixel-based protocols to a column count.
+const CELL_WIDTH: u32 = 8;
+/// Payload bytes per kitty graphics escape (the protocol's chunk limit).
+const KITTY_CHUNK: usize = 4096;
+/// Side of the checkerboard squares shown behind transparent pixels.
+const CHECKER: u32 = 4;
+
+/// How an image is drawn.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Protocol {
+    /// Pick from the environment (TERM, TERM_PROGRAM, ...), falling back to blocks
+    #[default]
+    Auto,
+    /// Unicode half blocks with 24-bit colors; works in any modern terminal
+    Blocks,
+    /// kitty graphics protocol (kitty, Ghostty, WezTerm)
+    Kitty,
+    /// iTerm2 inline images (iTerm2, WezTerm)
+    Iterm,
+    /// DEC sixel graphics (foot, mlterm, xterm -ti vt340, ...)
+    Sixel,
+}
+
+impl Protocol {
+    /// Resolve `Auto` from the environment of a terminal on stdout.
+    pub fn resolve(self, is_terminal: bool) -> Self {
+        if self != Self::Auto {
+            return self;
+        }
+        if !is_terminal {
+            return Self::Blocks;
+        }
+        let var = |name| env::var(name).unwrap_or_default();
+        let (term, program) = (var("TERM"), var("TERM_PROGRAM"));
+        if env::var_os("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty" || program == "ghostty"
+        {
+            Self::Kitty
+        } else if program == "iTerm.app" || program == "WezTerm" || var("LC_TERMINAL") == "iTerm2" {
+            Self::Iterm
+        } else if term.contains("sixel") || term.starts_with("foot") || term.starts_with("mlterm") {
+            Self::Sixel
+        } else {
+            Self::Blocks
+        }
+    }
+}
+
+/// Escape sequences drawing `image` `columns` cells wide, ending with a newline.
+/// `rows` caps the height in cells, when known.
+pub fn render(
+    image: &RgbaImage,
+    protocol: Protocol,
+    columns: u32,
+    rows: Option<u32>,
+) -> eyre::Result<String> {
+    let columns = columns.max(1);
+    Ok(match protocol {
+        Protocol::Auto | Protocol::Blocks => {
+            let max_height = rows.map_or(u32::MAX, |rows| rows * 2);
+            half_blocks(&on_checkerboard(&fit(image, columns, max_height)))
+        }
+        Protocol::Kitty => kitty(&png(image)?, columns),
+        Protocol::Iterm => iterm(&png(image)?, columns),
+        Protocol::Sixel => {
+            let max_height = rows.map_or(u32::MAX, |rows| rows * CELL_WIDTH * 2);
+            sixel(&on_checkerboard(&fit(
+                image,
+                columns * CELL_WIDTH,
+                max_height,
+            )))
+        }
+    })
+}
+
+/// Columns `image` covers at roughly one pixel per pixel, capped at `max`.
+pub fn natural_columns(image: &RgbaImage, protocol: Protocol, max: u32) -> u32 {
+    let columns = match protocol {
+        Protocol::Auto | Protocol::Blocks => image.width(),
+        _ => image.width().div_ceil(CELL_WIDTH),
+    };
+    columns.clamp(1, max.max(1))
+}
+
+/// Scale `image` to `width` pixels (at most `max_height` tall), keeping its
+/// aspect ratio.
+fn fit(image: &RgbaImage, width: u32, max_height: u32) -> RgbaImage {
+    let (w, h) = image.dimensions();
+    let scale = (width as f64 / w as f64).min(max_height as f64 / h as f64);
+    let width = ((w as f64 * scale).round() as u32).max(1);
+    let height = ((h as f64 * scale).round() as u32).max(1);
+    if (width, height) == (w, h) {
+        return image.clone();
+    }
+    // Nearest keeps upscaled texels crisp.
+    let filter = if width > w {
+        FilterType::Nearest
+    } else {
+        FilterType::Triangle
+    };
+    imageops::resize(image, width, height, filter)
+}
+
+/// `image` blended over a gray checkerboard, fully opaque.
+pub fn on_checkerboard(image: &RgbaImage) -> RgbaImage {
+    RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let Rgba([r, g, b, a]) = *image.get_pixel(x, y);
+        let back = if (x / CHECKER + y / CHECKER).is_multiple_of(2) {
+            0xCC
+        } else {
+            0x99
+        };
+        let blend = |c: u8| ((c as u32 * a as u32 + back * (255 - a as u32)) / 255) as u8;
+        Rgba([blend(r), blend(g), blend(b), 255])
+    })
+}
+
+/// Two pixel rows per line: the upper one as the foreground of `▀`, the lower
+/// one as its background. An odd last row is drawn on the default background.
+fn half_blocks(image: &RgbaImage) -> String {
+    let mut out = String::new();
+    for y in (0..image.height()).step_by(2) {
+        let (mut fg, mut bg) = (None, None);
+        for x in 0..image.width() {
+            let top = image.get_pixel(x, y);
+            if fg != Some(top) {
+                out += &format!("\x1b[38;2;{};{};{}m", top[0], top[1], top[2]);
+                fg = Some(top);
+            }
+            if y + 1 < image.height() {
+                let bottom = image.get_pixel(x, y + 1);
+                if bg != Some(bottom) {
+                    out += &format!("\x1b[48;2;{};{};{}m", bottom[0], bottom[1], bottom[2]);
+                    bg = Some(bottom);
+                }
+            }
+            out.push('▀');
+        }
+        out += "\x1b[0m\n";
+    }
+    out
+}
+
+fn png(image: &RgbaImage) -> eyre::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)?;
+    Ok(bytes)
+}
+
+/// Transmit and display a PNG in one go, split into chunks.
+fn kitty(png: &[u8], columns: u32) -> String {
+    let data = BASE64.encode(png);
+    let chunks = data.as_bytes().chunks(KITTY_CHUNK).collect::<Vec<_>>();
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let keys = if i == 0 {
+            format!("f=100,a=T,c={columns},m={more}")
+        } else {
+            format!("m={more}")
+        };
+        // Base64 is ASCII, so the chunks are valid UTF-8.
+        out += &format!("\x1b_G{keys};{}\x1b\\", std::str::from_utf8(chunk).unwrap());
+    }
+    out.push('\n');
+    out
+}
+
+fn iterm(png: &[u8], columns: u32) -> String {
+    format!(
+        "\x1b]1337;File=inline=1;size={};width={columns};preserveAspectRatio=1:{}\x07\n",
+        png.len(),
+        BASE64.encode(png)
+    )
+}
+
+/// Encode an opaque image as sixels with a 6x6x6 color cube palette.
+fn sixel(image: &RgbaImage) -> String {
+    let level = |c: u8| (c as u32 * 5 + 127) / 255;
+    let index = |p: &Rgba<u8>| (level(p[0]) * 36 + level(p[1]) * 6 + level(p[2])) as usize;
+
+    let (width, height) = image.dimensions();
+    let mut out = format!("\x1bP0;1;0q\"1;1;{width};{height}");
+    for i in 0..216 {
+        let percent = |l: u32| l * 100 / 5;
+        out += &format!(
+            "#{i};2;{};{};{}",
+            percent(i / 36),
+            percent(i / 6 % 6),
+            percent(i % 6)
+        );
+    }
+
+    for band in (0..height).step_by(6) {
+        let rows = (height - band).min(6);
+        // Sixel bits of each color, per column of the band.
+        let mut colors: Vec<Option<Vec<u8>>> = vec![None; 216];
+        for x in 0..width {
+            for row in 0..rows {
+                let bits = colors[index(image.get_pixel(x, band + row))]
+                    .get_or_insert_with(|| vec![0; width as usize]);
+                bits[x as usize] |= 1 << row;
+            }
+        }
+        for (color, bits) in colors.iter().enumerate() {
+            if let Some(bits) = bits {
+                out += &format!("#{color}");
+                push_sixel_run(&mut out, bits);
+                out.push('$');
+            }
+        }
+        out.push('-');
+    }
+    out += "\x1b\\\n";
+    out
+}
+
+/// Append one color's sixels, run-length encoded.
+fn push_sixel_run(out: &mut String, bits: &[u8]) {
+    let mut i = 0;
+    while i < bits.len() {
+        let run = bits[i..].iter().take_while(|&&b| b == bits[i]).count();
+        let c = (0x3F + bits[i]) as char;
+        if run > 3 {
+            out.push_str(&format!("!{run}{c}"));
+        } else {
+            out.extend(std::iter::repeat_n(c, run));
+        }
+        i += run;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn half_blocks_pair_rows_and_reuse_colors() {
+        let image = RgbaImage::from_fn(2, 3, |_, y| match y {
+            0 => Rgba([255, 0, 0, 255]),
+            1 => Rgba([0, 0, 255, 255]),
+            _ => Rgba([0, 255, 0, 255]),
+        });
+        assert_eq!(
+            half_blocks(&image),
+            "\x1b[38;2;255;0;0m\x1b[48;2;0;0;255m▀▀\x1b[0m\n\
+             \x1b[38;2;0;255;0m▀▀\x1b[0m\n"
+        );
+        // Transparent pixels show the checkerboard.
+        let clear = on_checkerboard(&RgbaImage::new(8, 1));
+        assert_eq!(clear.get_pixel(0, 0), &Rgba([0xCC, 0xCC, 0xCC, 255]));
+        assert_eq!(clear.get_pixel(4, 0), &Rgba([0x99, 0x99, 0x99, 255]));
+    }
+
+    #[test]
+    fn sixel_runs_are_length_encoded() {
+        let image = RgbaImage::from_pixel(5, 6, Rgba([255, 255, 255, 255]));
+        let out = sixel(&image);
+        assert!(out.starts_with("\x1bP0;1;0q\"1;1;5;6#0;2;0;0;0"));
+        assert!(out.ends_with("#215!5~$-\x1b\\\n"), "{out:?}");
+    }
+
+    #[test]
+    fn fits_images_to_the_available_cells() {
+        let image = RgbaImage::new(64, 32);
+        assert_eq!(fit(&image, 16, u32::MAX).dimensions(), (16, 8));
+        assert_eq!(fit(&image, 16, 4).dimensions(), (8, 4));
+        assert_eq!(natural_columns(&image, Protocol::Kitty, 80), 8);
+        assert_eq!(natural_columns(&image, Protocol::Blocks, 40), 40);
+    }
+}
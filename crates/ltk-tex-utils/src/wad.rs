@@ -0,0 +1,373 @@
+//! Reading textures straight out of WAD archives (`.wad.client`) and writing
+//! encoded textures back into them, so batch jobs never spill the archive's
+//! contents to disk.
+//!
+//! WAD chunks are keyed by the xxh64 of their lower-case path; names come from
+//! CDTB-style hashtables (`<hex hash> <path>` per line). Chunks without a known
+//! name are treated as textures when their data starts with the TEX magic, and
+//! are named after their hash (`<hash>.tex`).
+//!
+//! Entries are addressed as virtual paths `<archive>/<entry name>`, so they flow
+//! through `batch::Batch` like loose files.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{BufReader, Write};
+use std::path::{Component, Path, PathBuf};
+
+use ltk_tex_pipeline::io::{OutputFile, read_input};
+use ltk_wad::{Wad, WadBuilder, WadChunk, WadChunkBuilder, WadHash};
+
+use crate::walk::build_globset;
+
+/// Bytes of compressed chunk data read to sniff an unnamed chunk's magic.
+const SNIFF_RAW_LEN: usize = 64 * 1024;
+
+// Options selecting which entries of a WAD input are processed.
+#[derive(clap::Args, Debug, Default)]
+pub struct WadArgs {
+    /// Hashtable naming WAD entries (`<hex hash> <path>` per line, as shipped by
+    /// CDTB); may be given multiple times. Unnamed textures are named `<hash>.tex`
+    #[arg(long, value_name = "FILE")]
+    pub hashtable: Vec<PathBuf>,
+
+    /// Only process WAD entries whose path matches this glob
+    /// (e.g. `assets/characters/aatrox/**`); may be given multiple times
+    #[arg(long, value_name = "GLOB")]
+    pub filter: Vec<String>,
+
+    /// Only process the WAD entry with this path hash (hex); may be given
+    /// multiple times
+    #[arg(long = "hash", value_name = "HASH", value_parser = parse_hash)]
+    pub hashes: Vec<WadHash>,
+}
+
+fn parse_hash(s: &str) -> Result<WadHash, String> {
+    let s = s.trim_start_matches("0x");
+    WadHash::from_str_radix(s, 16).map_err(|e| format!("invalid WAD path hash '{s}': {e}"))
+}
+
+/// Whether `path` names a WAD archive (`.wad.client`, `.wad`).
+pub fn is_wad(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    name.ends_with(".wad.client") || name.ends_with(".wad")
+}
+
+/// Default folder decoded WAD entries are written under: the archive's path
+/// without its `.wad.client`/`.wad` suffix.
+pub fn default_output_dir(wad_path: &Path) -> PathBuf {
+    let name = wad_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let lower = name.to_lowercase();
+    let stem_len = [".wad.client", ".wad"]
+        .iter()
+        .find(|suffix| lower.ends_with(*suffix))
+        .map_or(name.len(), |suffix| name.len() - suffix.len());
+    wad_path.with_file_name(&name[..stem_len])
+}
+
+/// A mounted WAD and the texture entries selected from it.
+pub struct WadTextures {
+    path: PathBuf,
+    wad: RefCell<Wad<BufReader<File>>>,
+    /// Entry name -> chunk, in name order.
+    entries: BTreeMap<String, WadChunk>,
+}
+
+impl WadTextures {
+    pub fn open(path: &Path, args: &WadArgs) -> eyre::Result<Self> {
+        let names = load_hashtables(&args.hashtable)?;
+        let filter = build_globset(&args.filter)?;
+
+        let file = File::open(path)?;
+        let mut wad = Wad::mount(BufReader::new(file))?;
+        let chunks: Vec<WadChunk> = wad.chunks().iter().copied().collect();
+
+        let mut entries = BTreeMap::new();
+        for chunk in chunks {
+            let hash = chunk.path_hash();
+            if !args.hashes.is_empty() && !args.hashes.contains(&hash) {
+                continue;
+            }
+
+            let name = match names.get(&hash) {
+                Some(name) if name.to_lowercase().ends_with(".tex") => name.clone(),
+                Some(_) => continue,
+                None if is_tex_chunk(&mut wad, &chunk) => format!("{hash:016x}.tex"),
+                None => continue,
+            };
+            if filter.as_ref().is_some_and(|f| !f.is_match(&name)) {
+                continue;
+            }
+            entries.insert(name, chunk);
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            wad: RefCell::new(wad),
+            entries,
+        })
+    }
+
+    /// Whether `virtual_path` addresses an entry of this archive.
+    pub fn contains(&self, virtual_path: &Path) -> bool {
+        virtual_path.starts_with(&self.path)
+    }
+
+    /// Virtual paths (`<archive>/<entry name>`) of the selected textures.
+    pub fn virtual_paths(&self) -> Vec<PathBuf> {
+        self.entries
+            .keys()
+            .map(|name| self.path.join(name))
+            .collect()
+    }
+
+    /// Entry name of a virtual path returned by [`Self::virtual_paths`].
+    pub fn entry_name<'a>(&self, virtual_path: &'a Path) -> eyre::Result<&'a Path> {
+        virtual_path.strip_prefix(&self.path).map_err(|_| {
+            eyre::eyre!(
+                "{} is not inside {}",
+                virtual_path.display(),
+                self.path.display()
+            )
+        })
+    }
+
+    /// Decompressed data of the entry at `virtual_path`.
+    pub fn read(&self, virtual_path: &Path) -> eyre::Result<Vec<u8>> {
+        let name = self
+            .entry_name(virtual_path)?
+            .to_string_lossy()
+            .replace('\\', "/");
+        let chunk = self
+            .entries
+            .get(&name)
+            .ok_or_else(|| eyre::eyre!("no entry {name} in {}", self.path.display()))?;
+        Ok(self
+            .wad
+            .borrow_mut()
+            .load_chunk_decompressed(chunk)?
+            .into_vec())
+    }
+}
+
+/// Textures read in place by the commands that only inspect them: loose files
+/// plus the selected entries of WAD inputs.
+pub struct TextureSources {
+    wads: Vec<WadTextures>,
+}
+
+impl TextureSources {
+    /// Mount the WAD archives among `files`, returning the sources and every
+    /// texture to read: the loose files, then the entries of each archive.
+    pub fn open(files: Vec<PathBuf>, args: &WadArgs) -> eyre::Result<(Self, Vec<PathBuf>)> {
+        let (wad_files, mut textures): (Vec<PathBuf>, Vec<PathBuf>) =
+            files.into_iter().partition(|file| is_wad(file));
+        let wads = wad_files
+            .iter()
+            .map(|file| WadTextures::open(file, args))
+            .collect::<eyre::Result<Vec<_>>>()?;
+        textures.extend(wads.iter().flat_map(WadTextures::virtual_paths));
+        Ok((Self { wads }, textures))
+    }
+
+    /// Bytes of a texture returned by [`Self::open`] (`-` reads stdin).
+    pub fn read(&self, texture: &Path) -> eyre::Result<Vec<u8>> {
+        match self.wads.iter().find(|wad| wad.contains(texture)) {
+            Some(wad) => wad.read(texture),
+            None => Ok(read_input(&texture.to_string_lossy())?),
+        }
+    }
+}
+
+/// Whether an unnamed chunk holds a TEX, judged by the magic of its first bytes.
+fn is_tex_chunk(wad: &mut Wad<BufReader<File>>, chunk: &WadChunk) -> bool {
+    wad.load_chunk_raw_prefix(chunk, SNIFF_RAW_LEN)
+        .ok()
+        .and_then(|raw| ltk_wad::decompress_prefix(&raw, chunk.compression_type(), 4).ok())
+        .is_some_and(|magic| magic == b"TEX\0")
+}
+
+fn load_hashtables(paths: &[PathBuf]) -> eyre::Result<HashMap<WadHash, String>> {
+    let mut names = HashMap::new();
+    for path in paths {
+        let text = fs::read_to_string(path)
+            .map_err(|e| eyre::eyre!("failed to read hashtable {}: {e}", path.display()))?;
+        parse_hashtable(&text, &mut names);
+    }
+    Ok(names)
+}
+
+/// Add the `<hex hash> <path>` lines of `text` to `names`. Names that aren't
+/// plain relative paths (absolute, `..`) are skipped with a warning, since
+/// they end up joined onto output folders and virtual paths.
+fn parse_hashtable(text: &str, names: &mut HashMap<WadHash, String>) {
+    for line in text.lines() {
+        let Some((hash, name)) = line.trim().split_once(' ') else {
+            continue;
+        };
+        let Ok(hash) = WadHash::from_str_radix(hash, 16) else {
+            continue;
+        };
+        if !is_plain_relative(Path::new(&name.replace('\\', "/"))) {
+            tracing::warn!("skipping hashtable name {name}: not a relative path inside the WAD");
+            continue;
+        }
+        names.insert(hash, name.to_string());
+    }
+}
+
+/// Whether `path` is non-empty and made only of normal components (no root,
+/// prefix, `.` or `..`).
+fn is_plain_relative(path: &Path) -> bool {
+    path.components().next().is_some()
+        && path.components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// Path a file is stored under inside a WAD: relative to `root`, lower-case,
+/// forward slashes. `.` components are ignored on both sides; anything that
+/// isn't a plain relative path below `root` (absolute, `..`) is rejected.
+pub fn entry_path(file: &Path, root: &Path) -> eyre::Result<String> {
+    let normalize = |path: &Path| -> PathBuf {
+        path.components()
+            .filter(|c| *c != Component::CurDir)
+            .collect()
+    };
+    let normalized = normalize(file);
+    let relative = normalized
+        .strip_prefix(normalize(root))
+        .ok()
+        .filter(|relative| is_plain_relative(relative))
+        .ok_or_else(|| {
+            eyre::eyre!(
+                "{} is not under the WAD root {} (see --wad-root)",
+                file.display(),
+                root.display()
+            )
+        })?;
+    Ok(relative.to_string_lossy().replace('\\', "/").to_lowercase())
+}
+
+/// Write `textures` (entry path -> file data) into the WAD at `path`.
+///
+/// An existing archive keeps every chunk that isn't replaced. The new archive
+/// is built in a temporary file and moved into place once complete, since the
+/// old one is read while building.
+pub fn write_textures(path: &Path, textures: &BTreeMap<String, Vec<u8>>) -> eyre::Result<()> {
+    let replaced: HashMap<WadHash, &[u8]> = textures
+        .iter()
+        .map(|(name, data)| (WadHash::from(name.as_str()), data.as_slice()))
+        .collect();
+
+    let existing = if path.exists() {
+        Some(RefCell::new(Wad::mount(BufReader::new(File::open(path)?))?))
+    } else {
+        None
+    };
+
+    let mut builder = WadBuilder::default();
+    let mut hashes: Vec<WadHash> = replaced.keys().copied().collect();
+    if let Some(wad) = &existing {
+        let wad = wad.borrow();
+        hashes.extend(
+            wad.chunks()
+                .iter()
+                .map(|c| c.path_hash())
+                .filter(|h| !replaced.contains_key(h)),
+        );
+    }
+    for hash in hashes {
+        builder = builder.with_chunk(WadChunkBuilder::default().with_hash(hash));
+    }
+
+    let mut file = OutputFile::create(path)?;
+    builder.build_to_writer(&mut file, |hash, cursor| {
+        if let Some(data) = replaced.get(&hash) {
+            cursor.write_all(data)?;
+        } else if let Some(wad) = &existing {
+            let mut wad = wad.borrow_mut();
+            let chunk = *wad
+                .chunks()
+                .get(hash)
+                .expect("hash listed from this archive");
+            cursor.write_all(&wad.load_chunk_decompressed(&chunk)?)?;
+        }
+        Ok(())
+    })?;
+
+    // Release the old archive before it is replaced.
+    drop(existing);
+    Ok(file.commit()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_wad_paths_and_default_output_dir() {
+        assert!(is_wad(Path::new("DATA/FINAL/Champions/Aatrox.wad.client")));
+        assert!(is_wad(Path::new("mod.WAD")));
+        assert!(!is_wad(Path::new("texture.tex")));
+        assert_eq!(
+            default_output_dir(Path::new("wads/Aatrox.wad.client")),
+            PathBuf::from("wads/Aatrox")
+        );
+    }
+
+    #[test]
+    fn hashtables_skip_names_escaping_the_wad() {
+        let mut names = HashMap::new();
+        parse_hashtable(
+            "1 assets/a.tex\n2 ../../x.tex\n3 /etc/x.tex\n4 assets\\..\\..\\y.tex\n5 ./b.tex\n",
+            &mut names,
+        );
+        assert_eq!(names.len(), 1, "{names:?}");
+        assert_eq!(
+            names[&WadHash::from_str_radix("1", 16).unwrap()],
+            "assets/a.tex"
+        );
+    }
+
+    #[test]
+    fn entry_paths_are_relative_and_lowercase() {
+        assert_eq!(
+            entry_path(Path::new("./mod/ASSETS/Foo.tex"), Path::new("mod")).unwrap(),
+            "assets/foo.tex"
+        );
+        assert!(entry_path(Path::new("other/foo.tex"), Path::new("mod")).is_err());
+        assert!(entry_path(Path::new("/home/x/mod/a.tex"), Path::new(".")).is_err());
+        assert!(entry_path(Path::new("../mod/a.tex"), Path::new("..")).is_ok());
+        assert!(entry_path(Path::new("../mod/a.tex"), Path::new(".")).is_err());
+        assert!(entry_path(Path::new("mod/../a.tex"), Path::new("mod")).is_err());
+    }
+
+    #[test]
+    fn textures_roundtrip_through_a_wad() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.wad.client");
+
+        let mut textures = BTreeMap::new();
+        textures.insert("assets/a.tex".to_string(), b"TEX\0aaaa".to_vec());
+        textures.insert("assets/b.tex".to_string(), b"TEX\0bbbb".to_vec());
+        write_textures(&path, &textures).unwrap();
+
+        // Replace one entry; the other must survive the rebuild.
+        let mut update = BTreeMap::new();
+        update.insert("assets/b.tex".to_string(), b"TEX\0BBBB".to_vec());
+        write_textures(&path, &update).unwrap();
+
+        let wad = WadTextures::open(&path, &WadArgs::default()).unwrap();
+        let paths = wad.virtual_paths();
+        assert_eq!(paths.len(), 2);
+        let mut contents: Vec<Vec<u8>> = paths.iter().map(|p| wad.read(p).unwrap()).collect();
+        contents.sort();
+        assert_eq!(contents, vec![b"TEX\0BBBB".to_vec(), b"TEX\0aaaa".to_vec()]);
+    }
+}
@@ -0,0 +1,295 @@
+//! Expanding file and folder inputs into the list of files a batch converts.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ltk_tex_pipeline::io::is_stdio;
+
+// Options controlling which files are picked up from folder inputs.
+// Files passed explicitly are always kept.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct WalkArgs {
+    /// Only pick up files from folders that match this glob (e.g. `*_tx_cm.tex`);
+    /// may be given multiple times. Globs are matched against the path relative
+    /// to the folder input and against the file name
+    #[arg(long, value_name = "GLOB")]
+    pub include: Vec<String>,
+
+    /// Skip files and folders matching this glob (e.g. `_backup`); may be given
+    /// multiple times. Matched like `--include`
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// Only descend this many levels into folder inputs (1 = files directly inside)
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub max_depth: Option<usize>,
+
+    /// Skip files and folders listed in this `.gitignore`-style file; patterns
+    /// are relative to the file's folder. May be given multiple times
+    #[arg(long, value_name = "FILE")]
+    pub ignore_file: Vec<PathBuf>,
+
+    /// Descend into symlinked folders (symlinked files are always picked up).
+    /// Symlink loops and folders reached twice are only walked once
+    #[arg(long)]
+    pub follow_symlinks: bool,
+}
+
+/// Compiled [`WalkArgs`].
+#[derive(Default)]
+pub struct Walk {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+    max_depth: Option<usize>,
+    ignores: Vec<Gitignore>,
+    follow_symlinks: bool,
+}
+
+impl Walk {
+    pub fn new(args: &WalkArgs) -> eyre::Result<Self> {
+        let ignores = args
+            .ignore_file
+            .iter()
+            .map(|path| {
+                let path = std::path::absolute(path)?;
+                let mut builder = GitignoreBuilder::new(path.parent().unwrap_or(Path::new("/")));
+                if let Some(err) = builder.add(&path) {
+                    eyre::bail!("failed to read ignore file {}: {err}", path.display());
+                }
+                Ok(builder.build()?)
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            include: build_globset(&args.include)?,
+            exclude: build_globset(&args.exclude)?,
+            max_depth: args.max_depth,
+            ignores,
+            follow_symlinks: args.follow_symlinks,
+        })
+    }
+
+    /// Whether an entry at `relative` (to the folder input) is skipped.
+    fn is_excluded(&self, path: &Path, relative: &Path, is_dir: bool) -> bool {
+        if self
+            .exclude
+            .as_ref()
+            .is_some_and(|set| matches(set, relative))
+        {
+            return true;
+        }
+        if self.ignores.is_empty() {
+            return false;
+        }
+        let Ok(absolute) = std::path::absolute(path) else {
+            return false;
+        };
+        self.ignores
+            .iter()
+            .any(|ignore| ignore.matched(&absolute, is_dir).is_ignore())
+    }
+
+    fn is_included(&self, relative: &Path) -> bool {
+        self.include
+            .as_ref()
+            .is_none_or(|set| matches(set, relative))
+    }
+}
+
+fn matches(set: &GlobSet, relative: &Path) -> bool {
+    set.is_match(relative) || relative.file_name().is_some_and(|name| set.is_match(name))
+}
+
+pub fn build_globset(patterns: &[String]) -> eyre::Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern)?);
+    }
+    Ok(Some(builder.build()?))
+}
+
+/// Expand a mix of file and directory inputs into a flat list of files.
+///
+/// Directories are walked recursively in file-name order (so the result is the
+/// same on every machine), keeping files whose extension matches
+/// `dir_extensions` (case-insensitive) and that pass the `walk` filters.
+/// Explicitly listed files are kept as-is, and `-` is passed through as stdin.
+pub fn collect_input_files(
+    inputs: &[String],
+    dir_extensions: &[&str],
+    walk: &Walk,
+) -> eyre::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for input in inputs {
+        let path = PathBuf::from(input);
+        if is_stdio(&path) {
+            files.push(path);
+        } else if path.is_dir() {
+            let mut walker = DirWalker {
+                root: &path,
+                extensions: dir_extensions,
+                walk,
+                visited: HashSet::new(),
+                files: &mut files,
+            };
+            walker.visit(&path, 1)?;
+        } else if path.is_file() {
+            files.push(path);
+        } else {
+            eyre::bail!("input does not exist: {input}");
+        }
+    }
+    Ok(files)
+}
+
+/// Walks one folder input.
+struct DirWalker<'a> {
+    root: &'a Path,
+    extensions: &'a [&'a str],
+    walk: &'a Walk,
+    /// Canonical paths of the folders walked so far, to walk each only once
+    /// (and not loop forever) when following symlinks.
+    visited: HashSet<PathBuf>,
+    files: &'a mut Vec<PathBuf>,
+}
+
+impl DirWalker<'_> {
+    fn visit(&mut self, dir: &Path, depth: usize) -> eyre::Result<()> {
+        if self.walk.follow_symlinks && !self.visited.insert(dir.canonicalize()?) {
+            tracing::warn!(
+                "skipping {}: folder already walked (symlink loop?)",
+                dir.display()
+            );
+            return Ok(());
+        }
+
+        let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            let is_dir = if file_type.is_symlink() {
+                // Broken links are neither files nor folders and are skipped.
+                let is_dir = path.is_dir();
+                if is_dir && !self.walk.follow_symlinks {
+                    continue;
+                }
+                is_dir
+            } else {
+                file_type.is_dir()
+            };
+
+            let relative = path.strip_prefix(self.root).unwrap_or(&path);
+            if self.walk.is_excluded(&path, relative, is_dir) {
+                continue;
+            }
+            if is_dir {
+                if self.walk.max_depth.is_none_or(|max| depth < max) {
+                    self.visit(&path, depth + 1)?;
+                }
+            } else if path.is_file()
+                && path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| self.extensions.iter().any(|x| x.eq_ignore_ascii_case(e)))
+                && self.walk.is_included(relative)
+            {
+                self.files.push(path);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_folder_inputs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        for file in [
+            "a_tx_cm.tex",
+            "b.tex",
+            "sub/c_tx_cm.tex",
+            "_backup/d_tx_cm.tex",
+            "skip/e_tx_cm.tex",
+        ] {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, b"").unwrap();
+        }
+        fs::write(dir.join(".texignore"), "skip/\n").unwrap();
+
+        let collect = |args: WalkArgs| {
+            let inputs = [dir.to_string_lossy().into_owned()];
+            let mut files = collect_input_files(&inputs, &["tex"], &Walk::new(&args).unwrap())
+                .unwrap()
+                .iter()
+                .map(|f| {
+                    f.strip_prefix(dir)
+                        .unwrap()
+                        .to_string_lossy()
+                        .replace('\\', "/")
+                })
+                .collect::<Vec<_>>();
+            files.sort();
+            files
+        };
+
+        assert_eq!(
+            collect(WalkArgs {
+                include: vec!["*_tx_cm.tex".into()],
+                exclude: vec!["_backup".into()],
+                ignore_file: vec![dir.join(".texignore")],
+                ..Default::default()
+            }),
+            ["a_tx_cm.tex", "sub/c_tx_cm.tex"]
+        );
+        assert_eq!(
+            collect(WalkArgs {
+                max_depth: Some(1),
+                ..Default::default()
+            }),
+            ["a_tx_cm.tex", "b.tex"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_folders_are_opt_in_and_loop_free() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join("b")).unwrap();
+        fs::write(dir.join("b/x.tex"), b"").unwrap();
+        fs::write(dir.join("a.tex"), b"").unwrap();
+        std::os::unix::fs::symlink(dir, dir.join("b/loop")).unwrap();
+
+        let collect = |follow_symlinks| {
+            let inputs = [dir.to_string_lossy().into_owned()];
+            let walk = Walk::new(&WalkArgs {
+                follow_symlinks,
+                ..Default::default()
+            })
+            .unwrap();
+            collect_input_files(&inputs, &["tex"], &walk)
+                .unwrap()
+                .iter()
+                .map(|f| f.strip_prefix(dir).unwrap().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(collect(false), ["a.tex", "b/x.tex"]);
+        assert_eq!(collect(true), ["a.tex", "b/x.tex"]);
+    }
+}
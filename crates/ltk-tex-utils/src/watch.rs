@@ -0,0 +1,143 @@
+//! File watching for `encode --watch`.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime};
+
+use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode};
+
+/// Watch `inputs` (files and folders, as given on the command line) and call
+/// `on_change` with every batch of changed paths once `debounce` passed without
+/// further changes. Blocks until the watcher stops.
+///
+/// Folders are watched recursively. Files are watched through their parent
+/// folder, since editors often save by replacing the file, which would drop a
+/// watch placed on the file itself. Paths passed to `on_change` are absolute.
+pub fn watch(
+    inputs: &[String],
+    debounce: Duration,
+    mut on_change: impl FnMut(&BTreeSet<PathBuf>),
+) -> eyre::Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut debouncer = new_debouncer(debounce, tx)?;
+    for input in inputs {
+        let (path, mode) = watch_target(Path::new(input));
+        debouncer.watcher().watch(&path, mode)?;
+    }
+    tracing::info!(
+        "watching {} for changes (Ctrl+C to stop)",
+        inputs.join(", ")
+    );
+
+    for events in rx {
+        match events {
+            Ok(events) => {
+                let changed = events
+                    .into_iter()
+                    .map(|event| event.path)
+                    .collect::<BTreeSet<_>>();
+                on_change(&changed);
+            }
+            Err(err) => tracing::warn!("watch error: {err}"),
+        }
+    }
+    Ok(())
+}
+
+fn watch_target(path: &Path) -> (PathBuf, RecursiveMode) {
+    if path.is_dir() {
+        return (path.to_path_buf(), RecursiveMode::Recursive);
+    }
+    let parent = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    (parent.to_path_buf(), RecursiveMode::NonRecursive)
+}
+
+/// Tracks the modification time of each input at its last build, so events
+/// that don't change the content (e.g. the encoder opening the file to read it)
+/// don't trigger rebuilds.
+#[derive(Default)]
+pub struct Changes {
+    built: HashMap<PathBuf, SystemTime>,
+}
+
+impl Changes {
+    /// Record the current modification time of `files`.
+    pub fn record(&mut self, files: &[PathBuf]) {
+        for file in files {
+            if let Some(modified) = modified(file) {
+                self.built.insert(canonical(file), modified);
+            }
+        }
+    }
+
+    /// The `files` that are among the `changed` paths and were modified since
+    /// they were last recorded; these are recorded again.
+    pub fn take(&mut self, files: Vec<PathBuf>, changed: &BTreeSet<PathBuf>) -> Vec<PathBuf> {
+        let changed = changed
+            .iter()
+            .map(|p| canonical(p))
+            .collect::<BTreeSet<_>>();
+        files
+            .into_iter()
+            .filter(|file| {
+                let path = canonical(file);
+                if !changed.contains(&path) {
+                    return false;
+                }
+                let modified = modified(file);
+                let is_new = modified.is_some() && self.built.get(&path) != modified.as_ref();
+                if let Some(modified) = modified {
+                    self.built.insert(path, modified);
+                }
+                is_new
+            })
+            .collect()
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_modified_files_are_taken() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.png");
+        fs::write(&file, b"a").unwrap();
+
+        let mut changes = Changes::default();
+        changes.record(std::slice::from_ref(&file));
+        let events = BTreeSet::from([file.clone()]);
+        assert!(changes.take(vec![file.clone()], &events).is_empty());
+
+        let later = SystemTime::now() + Duration::from_secs(10);
+        fs::File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert_eq!(
+            changes.take(vec![file.clone()], &events),
+            vec![file.clone()]
+        );
+        assert!(
+            changes
+                .take(vec![file.clone()], &BTreeSet::new())
+                .is_empty()
+        );
+    }
+}